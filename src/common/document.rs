@@ -0,0 +1,135 @@
+/*! Document trait
+
+Both OSCAR 22.01 ([crate::oscar_doc::Document]) and OSCAR 23.01 ([crate::v3::Document]) documents implement [CorpusDocument],
+so that readers, filters, statistics and writers can be written once and used on either version.
+!*/
+use std::borrow::Cow;
+
+use url::Url;
+
+use super::{Extensions, Identification};
+
+/// Version-agnostic accessors over OSCAR documents.
+pub trait CorpusDocument {
+    /// Get a reference to the document's content.
+    fn content(&self) -> &str;
+
+    /// Set the document's content, and its `content-length` WARC header if present.
+    ///
+    /// Does not update sentence identifications.
+    fn set_content(&mut self, content: String);
+
+    /// Get a reference to the document-level identification.
+    fn identification(&self) -> &Identification<String>;
+
+    /// Set the document-level identification.
+    fn set_identification(&mut self, identification: Identification<String>);
+
+    /// Get a reference to the line-level identifications.
+    /// There should be one per line of [CorpusDocument::content].
+    fn sentence_identifications(&self) -> &[Option<Identification<String>>];
+
+    /// Set the line-level identifications.
+    fn set_sentence_identifications(
+        &mut self,
+        sentence_identifications: Vec<Option<Identification<String>>>,
+    );
+
+    /// Get the document's annotations (`annotation` in OSCAR 22.01, `quality_warnings` in OSCAR 23.01).
+    /// Returns an empty [Vec] if there are none.
+    fn annotations(&self) -> Vec<&str>;
+
+    /// Get the document's categories.
+    /// Returns an empty [Vec] if there are none, and always for OSCAR 22.01 documents.
+    fn categories(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// Get the document's harmful perplexity. Always [None] for OSCAR 22.01 documents.
+    fn harmful_pp(&self) -> Option<f32> {
+        None
+    }
+
+    /// Get the document's URL (`warc-target-uri` header).
+    fn url(&self) -> Option<Cow<'_, str>>;
+
+    /// Get the host of the document's URL, lowercased (ex. `www.example.com`).
+    fn host(&self) -> Option<String> {
+        self.url()
+            .and_then(|url| Url::parse(&url).ok())
+            .and_then(|url| url.host_str().map(str::to_lowercase))
+    }
+
+    /// Get the document's WARC record id (`warc-record-id` header).
+    fn record_id(&self) -> Option<Cow<'_, str>>;
+
+    /// Get a reference to the metadata fields unknown to oscar-io.
+    fn extensions(&self) -> &Extensions;
+
+    /// Get a mutable reference to the metadata fields unknown to oscar-io.
+    fn extensions_mut(&mut self) -> &mut Extensions;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::BufReader};
+
+    use oxilangtag::LanguageTag;
+    use warc::{Record, WarcHeader};
+
+    use super::CorpusDocument;
+    use crate::{oscar_doc, v3};
+
+    /// generic over document versions
+    fn summarize<D: CorpusDocument>(doc: &D) -> (usize, String, Option<String>) {
+        (
+            doc.content().lines().count(),
+            doc.identification().label().to_string(),
+            doc.record_id().map(|id| id.into_owned()),
+        )
+    }
+
+    #[test]
+    fn test_oscar_doc() {
+        let f = File::open("tests/res/data.jsonl").unwrap();
+        let doc = oscar_doc::Reader::new(BufReader::new(f))
+            .next()
+            .unwrap()
+            .unwrap();
+
+        let (nb_lines, label, record_id) = summarize(&doc);
+        assert_eq!(nb_lines, doc.sentence_identifications().len());
+        assert_eq!(label, "en");
+        assert_eq!(
+            record_id.as_deref(),
+            Some("<urn:uuid:c100fb99-8d71-49c6-a1d0-f24bca6590d8>")
+        );
+        assert_eq!(
+            CorpusDocument::url(&doc).as_deref(),
+            Some("https://en.wikipedia.org/wiki/Navi_Mumbai")
+        );
+        assert_eq!(
+            doc.annotations(),
+            vec!["short_sentences", "header", "footer"]
+        );
+    }
+
+    #[test]
+    fn test_v3() {
+        let record = Record::default().add_body("foo\nbar");
+        let record_id = record.warc_id().to_string();
+        let mut doc = v3::Document::from_record(record, v3::Metadata::default());
+        doc.metadata_mut().add_annotation("tiny".to_string());
+
+        let (nb_lines, label, id) = summarize(&doc);
+        assert_eq!(nb_lines, 2);
+        assert_eq!(label, LanguageTag::parse("en").unwrap().as_str());
+        assert_eq!(id, Some(record_id));
+        assert_eq!(doc.annotations(), vec!["tiny"]);
+        assert!(doc.warc_headers().get(&WarcHeader::TargetURI).is_none());
+        assert_eq!(CorpusDocument::url(&doc), None);
+
+        CorpusDocument::set_content(&mut doc, "baz".to_string());
+        assert_eq!(CorpusDocument::content(&doc), "baz");
+    }
+}
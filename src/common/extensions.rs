@@ -0,0 +1,119 @@
+/*! Metadata extensions
+
+Metadata fields that are unknown to oscar-io (toxicity scores, perplexities from other models, provenance...)
+are kept in an ordered [Extensions] map, and serialized back as-is next to the known fields.
+!*/
+use indexmap::IndexMap;
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// Ordered map of unknown metadata fields.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Extensions(IndexMap<String, Value>);
+
+impl Extensions {
+    /// Get a field, deserialized as `T`.
+    /// Fails if the field can't be deserialized into `T`.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Error> {
+        self.0
+            .get(key)
+            .map(|v| T::deserialize(v).map_err(Error::from))
+            .transpose()
+    }
+
+    /// Get a reference to the raw field value.
+    pub fn get_raw(&self, key: &str) -> Option<&Value> {
+        self.0.get(key)
+    }
+
+    /// Set a field, serializing `value`.
+    /// Existing fields keep their position.
+    pub fn set<T: Serialize>(&mut self, key: impl Into<String>, value: &T) -> Result<(), Error> {
+        self.0.insert(key.into(), serde_json::to_value(value)?);
+        Ok(())
+    }
+
+    /// Set a field from a raw value.
+    pub fn set_raw(&mut self, key: impl Into<String>, value: Value) {
+        self.0.insert(key.into(), value);
+    }
+
+    /// Remove a field, preserving the order of the other ones.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.0.shift_remove(key)
+    }
+
+    /// Whether a field is set.
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.contains_key(key)
+    }
+
+    /// Whether no field is set.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Number of fields.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Iterate over fields, in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.0.iter()
+    }
+}
+
+/// Extensions are a JSON object with arbitrary values.
+impl JsonSchema for Extensions {
+    fn schema_name() -> String {
+        "Extensions".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        <serde_json::Map<String, Value>>::json_schema(gen)
+    }
+}
+
+impl FromIterator<(String, Value)> for Extensions {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Extensions;
+
+    #[test]
+    fn test_typed() {
+        let mut ext = Extensions::default();
+        ext.set("toxicity", &0.5f64).unwrap();
+        ext.set("source", &"crawl-1").unwrap();
+
+        assert_eq!(ext.get::<f64>("toxicity").unwrap(), Some(0.5));
+        assert_eq!(
+            ext.get::<String>("source").unwrap().as_deref(),
+            Some("crawl-1")
+        );
+        assert_eq!(ext.get::<f64>("missing").unwrap(), None);
+        assert!(ext.get::<f64>("source").is_err());
+    }
+
+    #[test]
+    fn test_order() {
+        let mut ext: Extensions =
+            serde_json::from_str(r#"{"z":1,"a":{"b":[1,2]},"m":null}"#).unwrap();
+        ext.set("z", &2).unwrap();
+        ext.remove("a");
+        ext.set("b", &true).unwrap();
+        assert_eq!(
+            serde_json::to_string(&ext).unwrap(),
+            r#"{"z":2,"m":null,"b":true}"#
+        );
+    }
+}
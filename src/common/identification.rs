@@ -2,26 +2,49 @@
 
 All identifiers should implement [Identifier] to be useable in processing and pipelines.
 !*/
+use std::collections::HashMap;
 use std::ops::Deref;
 
 use crate::error::Error;
 
 use oxilangtag::{LanguageTag, LanguageTagParseError};
 
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
 use serde::{Deserialize, Serialize};
 
+/// Language identification: a BCP47 language tag and its probability.
+/// The label is serialized as a string.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Identification<T: Deref<Target = str> + Clone> {
     label: LanguageTag<T>,
     prob: f32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Lowest valid probability, enforced by validation and the JSON Schema.
+pub(crate) const MIN_PROB: f64 = 0.0;
+/// Highest valid probability, enforced by validation and the JSON Schema.
+/// Slightly above 1, since fastText probabilities can exceed it by a rounding error.
+pub(crate) const MAX_PROB: f64 = 1.001;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "Identification")]
 struct IdentificationSer {
     label: String,
+    #[schemars(range(min = "MIN_PROB", max = "MAX_PROB"))]
     prob: f32,
 }
 
+/// Uses the schema of the serialized form, where labels are strings.
+impl<T: Deref<Target = str> + Clone> JsonSchema for Identification<T> {
+    fn schema_name() -> String {
+        IdentificationSer::schema_name()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        IdentificationSer::json_schema(gen)
+    }
+}
+
 impl<T> From<Identification<T>> for IdentificationSer
 where
     T: Deref<Target = str> + Clone,
@@ -58,6 +81,51 @@ impl<T: Deref<Target = str> + Clone> Identification<T> {
     }
 }
 
+/// Derives document-level identifications from line identifications, weighted by line length (in characters).
+///
+/// Each language gets the sum of `length * prob` over its lines, divided by the total length of all lines
+/// (including unidentified ones).
+/// Returns every language found, ranked by decreasing probability: the first one is the document identification,
+/// and the other ones are secondary languages of multilingual documents.
+pub fn ranked_identifications<'a, I>(lines: I) -> Vec<Identification<String>>
+where
+    I: IntoIterator<Item = (&'a str, Option<&'a Identification<String>>)>,
+{
+    let mut total = 0f32;
+    let mut scores: HashMap<&str, f32> = HashMap::new();
+    for (line, id) in lines {
+        let len = line.chars().count() as f32;
+        total += len;
+        if let Some(id) = id {
+            *scores.entry(id.label().as_str()).or_insert(0.0) += len * id.prob();
+        }
+    }
+
+    let mut ranked: Vec<(&str, f32)> = scores.into_iter().collect();
+    // break ties by label to be deterministic
+    ranked.sort_by(|(l1, s1), (l2, s2)| s2.total_cmp(s1).then_with(|| l1.cmp(l2)));
+    ranked
+        .into_iter()
+        .filter_map(|(label, score)| {
+            let prob = if total > 0.0 { score / total } else { 0.0 };
+            LanguageTag::parse(label.to_string())
+                .ok()
+                .map(|label| Identification::new(label, prob))
+        })
+        .collect()
+}
+
+/// Derives a document-level identification from line identifications, weighted by line length.
+/// Returns [None] if no line is identified.
+///
+/// See [ranked_identifications].
+pub fn weighted_identification<'a, I>(lines: I) -> Option<Identification<String>>
+where
+    I: IntoIterator<Item = (&'a str, Option<&'a Identification<String>>)>,
+{
+    ranked_identifications(lines).into_iter().next()
+}
+
 pub trait Identifier<T: Deref<Target = str> + Clone> {
     /// returns a language identification token (from [crate::lang::LANG]).
     fn identify(&self, sentence: T) -> Result<Option<Identification<T>>, Error>;
@@ -65,5 +133,38 @@ pub trait Identifier<T: Deref<Target = str> + Clone> {
 
 #[cfg(test)]
 mod tests {
-    // TODO tests
+    use oxilangtag::LanguageTag;
+
+    use super::{ranked_identifications, weighted_identification, Identification};
+
+    fn id(label: &str, prob: f32) -> Identification<String> {
+        Identification::new(LanguageTag::parse(label.to_string()).unwrap(), prob)
+    }
+
+    #[test]
+    fn test_ranked() {
+        let (fr, en, de) = (id("fr", 1.0), id("en", 0.5), id("de", 1.0));
+        let lines = vec![
+            ("un texte assez long", Some(&fr)),
+            ("some text", Some(&en)),
+            ("ein", Some(&de)),
+            ("1234", None),
+            ("", Some(&de)),
+        ];
+
+        let ranked = ranked_identifications(lines.clone());
+        let labels: Vec<&str> = ranked.iter().map(|id| id.label().as_str()).collect();
+        assert_eq!(labels, vec!["fr", "en", "de"]);
+        assert_eq!(*ranked[0].prob(), 19.0 / 35.0);
+        assert_eq!(*ranked[1].prob(), 4.5 / 35.0);
+        assert_eq!(*ranked[2].prob(), 3.0 / 35.0);
+
+        assert_eq!(weighted_identification(lines), Some(ranked[0].clone()));
+    }
+
+    #[test]
+    fn test_unidentified() {
+        assert_eq!(weighted_identification(vec![("foo", None)]), None);
+        assert!(ranked_identifications(vec![]).is_empty());
+    }
 }
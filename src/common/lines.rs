@@ -0,0 +1,277 @@
+/*! Line-level view of documents
+
+Sentence identifications are aligned with the lines of the content (as split by [str::lines]).
+[DocumentLines] exposes that alignment and provides line editing that keeps both in sync,
+for any [CorpusDocument].
+
+Edited content is joined with `\n`: `\r\n` line endings and trailing newlines are not kept.
+!*/
+use std::collections::HashSet;
+
+use crate::error::{Error, ValidationError};
+
+use super::{CorpusDocument, Identification};
+
+/// Iterator over `(line, identification)` pairs of a document.
+pub struct Lines<'a> {
+    lines: std::str::Lines<'a>,
+    identifications: std::slice::Iter<'a, Option<Identification<String>>>,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = (&'a str, Option<&'a Identification<String>>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.lines.next()?;
+        let identification = self.identifications.next()?;
+        Some((line, identification.as_ref()))
+    }
+}
+
+/// Line-level access and editing, implemented for every [CorpusDocument].
+///
+/// Edits go through [CorpusDocument::set_content], which keeps the `content-length` header up to date.
+pub trait DocumentLines: CorpusDocument + Sized {
+    /// Checks that there is one sentence identification per line.
+    ///
+    /// Fails with a [ValidationError::SentenceCountMismatch] otherwise.
+    fn check_lines(&self) -> Result<(), Error> {
+        let lines = self.content().lines().count();
+        let identifications = self.sentence_identifications().len();
+        if lines == identifications {
+            Ok(())
+        } else {
+            Err(Error::Validation(vec![
+                ValidationError::SentenceCountMismatch {
+                    lines,
+                    identifications,
+                },
+            ]))
+        }
+    }
+
+    /// Iterate over lines and their identifications.
+    ///
+    /// Fails if lines and identifications are not aligned (see [DocumentLines::check_lines]).
+    fn lines(&self) -> Result<Lines<'_>, Error> {
+        self.check_lines()?;
+        Ok(Lines {
+            lines: self.content().lines(),
+            identifications: self.sentence_identifications().iter(),
+        })
+    }
+
+    /// Get the languages of the document, ranked by length-weighted probability
+    /// (see [crate::common::ranked_identifications]).
+    fn ranked_identifications(&self) -> Result<Vec<Identification<String>>, Error> {
+        Ok(super::ranked_identifications(self.lines()?))
+    }
+
+    /// Recompute the document identification from the line identifications,
+    /// weighted by line length (see [crate::common::weighted_identification]).
+    ///
+    /// Returns the new identification, or [None] if no line is identified,
+    /// in which case the document identification is left untouched.
+    fn recompute_identification(&mut self) -> Result<Option<Identification<String>>, Error> {
+        let identification = super::weighted_identification(self.lines()?);
+        if let Some(identification) = &identification {
+            self.set_identification(identification.clone());
+        }
+        Ok(identification)
+    }
+
+    /// Keep only the lines for which `f` returns `true`.
+    /// `f` gets the line index, the line and its identification.
+    ///
+    /// Returns the number of removed lines.
+    fn retain_lines<F>(&mut self, mut f: F) -> Result<usize, Error>
+    where
+        F: FnMut(usize, &str, Option<&Identification<String>>) -> bool,
+    {
+        let (kept_lines, kept_ids): (Vec<&str>, Vec<Option<Identification<String>>>) = self
+            .lines()?
+            .enumerate()
+            .filter(|(idx, (line, id))| f(*idx, line, *id))
+            .map(|(_, (line, id))| (line, id.cloned()))
+            .unzip();
+
+        let removed = self.sentence_identifications().len() - kept_ids.len();
+        if removed > 0 {
+            let content = kept_lines.join("\n");
+            self.set_content(content);
+            self.set_sentence_identifications(kept_ids);
+        }
+        Ok(removed)
+    }
+
+    /// Remove the lines at the provided indices. Out of range indices are ignored.
+    ///
+    /// Returns the number of removed lines.
+    fn drop_lines(&mut self, indices: &[usize]) -> Result<usize, Error> {
+        let indices: HashSet<usize> = indices.iter().copied().collect();
+        self.retain_lines(|idx, _, _| !indices.contains(&idx))
+    }
+
+    /// Keep only the lines identified as `lang` (ex. `"fr"`).
+    /// Unidentified lines are removed.
+    ///
+    /// Returns the number of removed lines.
+    fn keep_language(&mut self, lang: &str) -> Result<usize, Error> {
+        self.retain_lines(|_, _, id| id.map(|id| id.label().as_str()) == Some(lang))
+    }
+
+    /// Replace the line at `index` and its identification.
+    ///
+    /// Fails if `index` is out of range or if `line` contains a line break.
+    fn set_line(
+        &mut self,
+        index: usize,
+        line: &str,
+        identification: Option<Identification<String>>,
+    ) -> Result<(), Error> {
+        if line.contains(['\n', '\r']) {
+            return Err(Error::Custom(format!(
+                "line {index} replacement contains a line break"
+            )));
+        }
+        let nb_lines = self.lines()?.count();
+        if index >= nb_lines {
+            return Err(Error::Custom(format!(
+                "line {index} out of range ({nb_lines} lines)"
+            )));
+        }
+
+        let content = self
+            .content()
+            .lines()
+            .enumerate()
+            .map(|(idx, l)| if idx == index { line } else { l })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut identifications = self.sentence_identifications().to_vec();
+        identifications[index] = identification;
+
+        self.set_content(content);
+        self.set_sentence_identifications(identifications);
+        Ok(())
+    }
+}
+
+impl<D: CorpusDocument> DocumentLines for D {}
+
+#[cfg(test)]
+mod tests {
+    use super::DocumentLines;
+    use crate::common::CorpusDocument;
+    use crate::error::Error;
+    use crate::error::ValidationError;
+    use crate::test_utils::id;
+    use crate::v3::{Document, Metadata};
+
+    fn get_doc() -> Document {
+        let ids = vec![
+            Some(id("fr", 0.9)),
+            Some(id("en", 0.9)),
+            None,
+            Some(id("fr", 0.9)),
+        ];
+        let metadata = Metadata::builder(id("fr", 0.9))
+            .sentence_identifications(ids)
+            .build()
+            .unwrap();
+        Document::builder("bonjour\nhello\n1234\r\nau revoir\n")
+            .metadata(metadata)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_lines() {
+        let doc = get_doc();
+        let labels: Vec<(&str, Option<&str>)> = doc
+            .lines()
+            .unwrap()
+            .map(|(line, id)| (line, id.map(|id| id.label().as_str())))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                ("bonjour", Some("fr")),
+                ("hello", Some("en")),
+                ("1234", None),
+                ("au revoir", Some("fr")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mismatch() {
+        let mut doc = get_doc();
+        doc.set_content("one line".to_string());
+        match doc.lines().err() {
+            Some(Error::Validation(errors)) => assert_eq!(
+                errors,
+                vec![ValidationError::SentenceCountMismatch {
+                    lines: 1,
+                    identifications: 4
+                }]
+            ),
+            other => panic!("expected a validation error, got {other:?}"),
+        }
+        assert!(doc.drop_lines(&[0]).is_err());
+    }
+
+    #[test]
+    fn test_keep_language() {
+        let mut doc = get_doc();
+        assert_eq!(doc.keep_language("fr").unwrap(), 2);
+        assert_eq!(doc.content(), "bonjour\nau revoir");
+        assert_eq!(doc.sentence_identifications().len(), 2);
+        assert!(doc.check_lines().is_ok());
+    }
+
+    #[test]
+    fn test_drop_lines() {
+        let mut doc = get_doc();
+        assert_eq!(doc.drop_lines(&[1, 2, 42]).unwrap(), 2);
+        assert_eq!(doc.content(), "bonjour\nau revoir");
+        assert_eq!(
+            doc.warc_headers().content_length().unwrap(),
+            Some(doc.content().len() as u64)
+        );
+        assert_eq!(doc.drop_lines(&[]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_recompute_identification() {
+        let mut doc = get_doc();
+        let ranked = doc.ranked_identifications().unwrap();
+        let labels: Vec<&str> = ranked.iter().map(|id| id.label().as_str()).collect();
+        assert_eq!(labels, vec!["fr", "en"]);
+
+        doc.keep_language("en").unwrap();
+        let id = doc.recompute_identification().unwrap().unwrap();
+        assert_eq!(id.label().as_str(), "en");
+        assert_eq!(doc.identification(), &id);
+
+        doc.set_line(0, "hello", None).unwrap();
+        assert_eq!(doc.recompute_identification().unwrap(), None);
+        assert_eq!(doc.identification(), &id);
+    }
+
+    #[test]
+    fn test_set_line() {
+        let mut doc = get_doc();
+        doc.set_line(2, "hallo", Some(id("de", 0.9))).unwrap();
+        assert_eq!(doc.content(), "bonjour\nhello\nhallo\nau revoir");
+        assert_eq!(
+            doc.sentence_identifications()[2]
+                .as_ref()
+                .map(|id| id.label().as_str()),
+            Some("de")
+        );
+
+        assert!(doc.set_line(4, "out", None).is_err());
+        assert!(doc.set_line(0, "two\nlines", None).is_err());
+    }
+}
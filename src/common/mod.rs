@@ -1,3 +1,16 @@
 //! Common types used in multiple (if not all) different OSCAR Corpus versions.
+mod document;
+mod extensions;
 mod identification;
+mod lines;
+pub(crate) mod tmp;
+mod warc_headers;
 pub use identification::Identification;
+pub use identification::Identifier;
+pub use identification::{ranked_identifications, weighted_identification};
+pub(crate) use identification::{MAX_PROB, MIN_PROB};
+
+pub use document::CorpusDocument;
+pub use extensions::Extensions;
+pub use lines::{DocumentLines, Lines};
+pub use warc_headers::{BlockDigest, WarcHeaders};
@@ -0,0 +1,35 @@
+//! Temporary directories for disk-backed algorithms (shuffle, sort...).
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::Error;
+
+/// Uniquely named directory, removed with its contents on drop.
+pub(crate) struct TmpDir(PathBuf);
+
+impl TmpDir {
+    /// Create a directory in `parent` ([std::env::temp_dir] if [None]).
+    pub(crate) fn create(parent: Option<&Path>, prefix: &str) -> Result<Self, Error> {
+        let parent = parent.map_or_else(std::env::temp_dir, Path::to_path_buf);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        let dir = parent.join(format!(".oscar-io-{prefix}-{}-{nanos}", std::process::id()));
+        std::fs::create_dir_all(&parent)?;
+        std::fs::create_dir(&dir)?;
+        Ok(Self(dir))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TmpDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.0) {
+            log::warn!("could not remove {:?}: {e}", self.0);
+        }
+    }
+}
@@ -0,0 +1,530 @@
+/*! WARC headers
+
+[WarcHeaders] wraps the raw header map found in WARC records, and provides typed getters on top of it.
+It dereferences to the inner [HashMap], so raw values can still be accessed by [WarcHeader].
+
+On disk, headers are (de)serialized as a JSON object of strings, with header names as keys.
+Header names are case-insensitive: lookups ignore case, but names are written back in the case they were read with.
+!*/
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::{Deref, DerefMut};
+
+use chrono::{DateTime, FixedOffset, SecondsFormat, TimeZone};
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use url::Url;
+use uuid::Uuid;
+use warc::WarcHeader;
+
+use crate::error::Error;
+
+/// Header holding the languages identified by Common Crawl.
+const IDENTIFIED_CONTENT_LANGUAGE: &str = "warc-identified-content-language";
+
+/// WARC headers of a document, with typed getters.
+#[derive(Clone, Default)]
+pub struct WarcHeaders {
+    headers: HashMap<WarcHeader, Vec<u8>>,
+    /// Names as they were read, for headers not written in lowercase.
+    names: HashMap<WarcHeader, String>,
+}
+
+/// Digest of a WARC record block (`warc-block-digest` header), such as `sha1:AAN5C7C7I2JOXM5ZYB5YNFPRC5N6GJES`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockDigest {
+    algorithm: String,
+    digest: Vec<u8>,
+}
+
+impl BlockDigest {
+    /// Get a reference to the digest algorithm (ex. `sha1`).
+    pub fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    /// Get a reference to the decoded digest.
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+}
+
+impl Display for BlockDigest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.algorithm, base32_encode(&self.digest))
+    }
+}
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// RFC 4648 base32 decoding (padding is ignored).
+fn base32_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer = 0u64;
+    let mut bits = 0;
+    for c in s.trim_end_matches('=').bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&x| x == c.to_ascii_uppercase())? as u64;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
+/// RFC 4648 base32 encoding, without padding.
+fn base32_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 8 / 5 + 1);
+    let mut buffer = 0u64;
+    let mut bits = 0;
+    for &b in bytes {
+        buffer = (buffer << 8) | b as u64;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    out
+}
+
+/// Parses a record id of the form `<urn:uuid:...>`.
+fn parse_record_id(header: WarcHeader, id: &str) -> Result<Uuid, Error> {
+    id.strip_prefix("<urn:uuid:")
+        .and_then(|id| id.strip_suffix('>'))
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .ok_or_else(|| Error::MalformedHeader(header, format!("invalid record id: {id}")))
+}
+
+impl WarcHeaders {
+    /// Get a header value as a [str].
+    /// Fails if the value is not valid UTF-8.
+    pub fn get_str(&self, header: &WarcHeader) -> Result<Option<&str>, Error> {
+        self.headers
+            .get(header)
+            .map(|v| {
+                std::str::from_utf8(v).map_err(|_| {
+                    Error::MalformedHeader(header.clone(), "not a UTF-8 string".to_string())
+                })
+            })
+            .transpose()
+    }
+
+    /// Parses `warc-date` as an RFC 3339 date.
+    pub fn date(&self) -> Result<Option<DateTime<FixedOffset>>, Error> {
+        self.get_str(&WarcHeader::Date)?
+            .map(|date| {
+                DateTime::parse_from_rfc3339(date)
+                    .map_err(|e| Error::MalformedHeader(WarcHeader::Date, format!("{e}: {date}")))
+            })
+            .transpose()
+    }
+
+    /// Parses `warc-target-uri` as an [Url].
+    pub fn target_uri(&self) -> Result<Option<Url>, Error> {
+        self.get_str(&WarcHeader::TargetURI)?
+            .map(|url| {
+                Url::parse(url).map_err(|e| {
+                    Error::MalformedHeader(WarcHeader::TargetURI, format!("{e}: {url}"))
+                })
+            })
+            .transpose()
+    }
+
+    /// Get the host of `warc-target-uri`.
+    /// Can be an IP address.
+    pub fn host(&self) -> Result<Option<String>, Error> {
+        Ok(self
+            .target_uri()?
+            .and_then(|url| url.host_str().map(String::from)))
+    }
+
+    /// Get the domain name of `warc-target-uri`.
+    /// Returns [None] if the host is an IP address.
+    pub fn domain(&self) -> Result<Option<String>, Error> {
+        Ok(self
+            .target_uri()?
+            .and_then(|url| url.domain().map(String::from)))
+    }
+
+    /// Parses `warc-record-id` as an [Uuid].
+    pub fn record_id(&self) -> Result<Option<Uuid>, Error> {
+        self.get_str(&WarcHeader::RecordID)?
+            .map(|id| parse_record_id(WarcHeader::RecordID, id))
+            .transpose()
+    }
+
+    /// Parses `warc-refers-to` as an [Uuid].
+    pub fn refers_to(&self) -> Result<Option<Uuid>, Error> {
+        self.get_str(&WarcHeader::RefersTo)?
+            .map(|id| parse_record_id(WarcHeader::RefersTo, id))
+            .transpose()
+    }
+
+    /// Parses `warc-block-digest`.
+    /// The digest is expected to be base32-encoded, which is the case in Common Crawl files.
+    pub fn block_digest(&self) -> Result<Option<BlockDigest>, Error> {
+        self.get_str(&WarcHeader::BlockDigest)?
+            .map(|digest| {
+                let malformed = || {
+                    Error::MalformedHeader(
+                        WarcHeader::BlockDigest,
+                        format!("invalid block digest: {digest}"),
+                    )
+                };
+                let (algorithm, encoded) = digest.split_once(':').ok_or_else(malformed)?;
+                let digest = base32_decode(encoded).ok_or_else(malformed)?;
+                Ok(BlockDigest {
+                    algorithm: algorithm.to_string(),
+                    digest,
+                })
+            })
+            .transpose()
+    }
+
+    /// Parses `content-length`.
+    pub fn content_length(&self) -> Result<Option<u64>, Error> {
+        self.get_str(&WarcHeader::ContentLength)?
+            .map(|length| {
+                length.parse().map_err(|_| {
+                    Error::MalformedHeader(
+                        WarcHeader::ContentLength,
+                        format!("invalid length: {length}"),
+                    )
+                })
+            })
+            .transpose()
+    }
+
+    /// Get the languages of `warc-identified-content-language` (ex. `["fra", "eng"]`).
+    pub fn identified_content_language(&self) -> Result<Option<Vec<String>>, Error> {
+        let header = WarcHeader::Unknown(IDENTIFIED_CONTENT_LANGUAGE.to_string());
+        Ok(self.get_str(&header)?.map(|langs| {
+            langs
+                .split(',')
+                .map(|lang| lang.trim().to_string())
+                .filter(|lang| !lang.is_empty())
+                .collect()
+        }))
+    }
+
+    /// Sets `warc-date`, formatted as RFC 3339.
+    pub fn set_date<Tz: TimeZone>(&mut self, date: &DateTime<Tz>)
+    where
+        Tz::Offset: Display,
+    {
+        self.headers.insert(
+            WarcHeader::Date,
+            date.to_rfc3339_opts(SecondsFormat::Secs, true).into(),
+        );
+    }
+
+    /// Sets `warc-target-uri`.
+    pub fn set_target_uri(&mut self, url: &Url) {
+        self.headers
+            .insert(WarcHeader::TargetURI, url.as_str().as_bytes().to_vec());
+    }
+
+    /// Sets `warc-record-id`, formatted as `<urn:uuid:...>`.
+    pub fn set_record_id(&mut self, id: &Uuid) {
+        self.headers
+            .insert(WarcHeader::RecordID, format!("<urn:uuid:{id}>").into());
+    }
+
+    /// Sets `warc-refers-to`, formatted as `<urn:uuid:...>`.
+    pub fn set_refers_to(&mut self, id: &Uuid) {
+        self.headers
+            .insert(WarcHeader::RefersTo, format!("<urn:uuid:{id}>").into());
+    }
+
+    /// Sets `warc-block-digest`.
+    pub fn set_block_digest(&mut self, digest: &BlockDigest) {
+        self.headers
+            .insert(WarcHeader::BlockDigest, digest.to_string().into());
+    }
+
+    /// Sets `content-length`.
+    pub fn set_content_length(&mut self, length: u64) {
+        self.headers
+            .insert(WarcHeader::ContentLength, length.to_string().into());
+    }
+
+    /// Sets `warc-identified-content-language` (ex. `["fra", "eng"]`).
+    pub fn set_identified_content_language<S: AsRef<str>>(&mut self, langs: &[S]) {
+        let langs: Vec<&str> = langs.iter().map(AsRef::as_ref).collect();
+        self.headers.insert(
+            WarcHeader::Unknown(IDENTIFIED_CONTENT_LANGUAGE.to_string()),
+            langs.join(",").into(),
+        );
+    }
+
+    /// Get the inner header map.
+    pub fn into_inner(self) -> HashMap<WarcHeader, Vec<u8>> {
+        self.headers
+    }
+
+    /// Get the name of a header, in the case it was read with.
+    fn name<'a>(&'a self, header: &WarcHeader) -> Cow<'a, str> {
+        match self.names.get(header) {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(header.to_string()),
+        }
+    }
+}
+
+/// Header names are compared case-insensitively.
+impl PartialEq for WarcHeaders {
+    fn eq(&self, other: &Self) -> bool {
+        self.headers == other.headers
+    }
+}
+
+impl Deref for WarcHeaders {
+    type Target = HashMap<WarcHeader, Vec<u8>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.headers
+    }
+}
+
+impl DerefMut for WarcHeaders {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.headers
+    }
+}
+
+impl From<HashMap<WarcHeader, Vec<u8>>> for WarcHeaders {
+    fn from(headers: HashMap<WarcHeader, Vec<u8>>) -> Self {
+        Self {
+            headers,
+            names: HashMap::new(),
+        }
+    }
+}
+
+impl From<WarcHeaders> for HashMap<WarcHeader, Vec<u8>> {
+    fn from(headers: WarcHeaders) -> Self {
+        headers.headers
+    }
+}
+
+impl FromIterator<(WarcHeader, Vec<u8>)> for WarcHeaders {
+    fn from_iter<I: IntoIterator<Item = (WarcHeader, Vec<u8>)>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<HashMap<_, _>>())
+    }
+}
+
+impl IntoIterator for WarcHeaders {
+    type Item = (WarcHeader, Vec<u8>);
+    type IntoIter = std::collections::hash_map::IntoIter<WarcHeader, Vec<u8>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.headers.into_iter()
+    }
+}
+
+/// Values are serialized as (lossy) strings.
+impl Serialize for WarcHeaders {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            self.headers
+                .iter()
+                .map(|(k, v)| (self.name(k), String::from_utf8_lossy(v))),
+        )
+    }
+}
+
+/// Header names keep their case.
+/// If several keys only differ by case, only one of their values is kept.
+impl<'de> Deserialize<'de> for WarcHeaders {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw: HashMap<String, String> = HashMap::deserialize(deserializer)?;
+        let mut headers = Self::default();
+        for (name, value) in raw {
+            let header = WarcHeader::from(&name);
+            if name != header.to_string() {
+                headers.names.insert(header.clone(), name);
+            }
+            headers.headers.insert(header, value.into_bytes());
+        }
+        Ok(headers)
+    }
+}
+
+/// Headers are a JSON object of strings.
+impl JsonSchema for WarcHeaders {
+    fn schema_name() -> String {
+        "WarcHeaders".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        <HashMap<String, String>>::json_schema(gen)
+    }
+}
+
+/// Converts values from [Vec<u8>] to [String] for easier readability.
+impl std::fmt::Debug for WarcHeaders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(
+                self.headers
+                    .iter()
+                    .map(|(k, v)| (self.name(k), String::from_utf8_lossy(v))),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use warc::WarcHeader;
+
+    use super::{base32_decode, base32_encode, WarcHeaders};
+
+    fn get_headers() -> WarcHeaders {
+        serde_json::from_str(
+            r#"{
+                 "warc-date":"2021-02-24T18:50:04Z",
+                 "warc-identified-content-language":"afr,eng",
+                 "content-type":"text/plain",
+                 "warc-record-id":"<urn:uuid:4c2d4cbb-24ef-4885-9516-d131fc15af2e>",
+                 "content-length":"4891",
+                 "warc-type":"conversion",
+                 "warc-refers-to":"<urn:uuid:94ff8c3f-838f-44e6-ba2a-a47262025819>",
+                 "warc-block-digest":"sha1:UMHO34U75QPBAT2DB756CLZYMVGQZC6G",
+                 "warc-target-uri":"https://www.foo.bar/baz?quux=1"
+             }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_getters() {
+        let headers = get_headers();
+        assert_eq!(
+            headers.date().unwrap().unwrap().to_rfc3339(),
+            "2021-02-24T18:50:04+00:00"
+        );
+        assert_eq!(
+            headers.target_uri().unwrap().unwrap().path(),
+            "/baz".to_string()
+        );
+        assert_eq!(headers.domain().unwrap().as_deref(), Some("www.foo.bar"));
+        assert_eq!(
+            headers.record_id().unwrap().unwrap().to_string(),
+            "4c2d4cbb-24ef-4885-9516-d131fc15af2e"
+        );
+        assert_eq!(
+            headers.refers_to().unwrap().unwrap().to_string(),
+            "94ff8c3f-838f-44e6-ba2a-a47262025819"
+        );
+        assert_eq!(headers.content_length().unwrap(), Some(4891));
+        assert_eq!(
+            headers.identified_content_language().unwrap(),
+            Some(vec!["afr".to_string(), "eng".to_string()])
+        );
+
+        let digest = headers.block_digest().unwrap().unwrap();
+        assert_eq!(digest.algorithm(), "sha1");
+        assert_eq!(digest.digest().len(), 20);
+        assert_eq!(digest.to_string(), "sha1:UMHO34U75QPBAT2DB756CLZYMVGQZC6G");
+    }
+
+    #[test]
+    fn test_malformed() {
+        let mut headers = get_headers();
+        headers.insert(WarcHeader::Date, b"yesterday".to_vec());
+        headers.insert(WarcHeader::RecordID, b"4c2d4cbb".to_vec());
+        headers.insert(WarcHeader::TargetURI, vec![0xff, 0xfe]);
+        assert!(headers.date().is_err());
+        assert!(headers.record_id().is_err());
+        assert!(headers.target_uri().is_err());
+
+        headers.remove(&WarcHeader::Date);
+        assert!(headers.date().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_setters() {
+        let expected = get_headers();
+        let mut headers = WarcHeaders::default();
+        headers.set_date(&expected.date().unwrap().unwrap());
+        headers.set_target_uri(&expected.target_uri().unwrap().unwrap());
+        headers.set_record_id(&expected.record_id().unwrap().unwrap());
+        headers.set_refers_to(&expected.refers_to().unwrap().unwrap());
+        headers.set_block_digest(&expected.block_digest().unwrap().unwrap());
+        headers.set_content_length(4891);
+        headers.set_identified_content_language(&["afr", "eng"]);
+
+        for (header, value) in headers.iter() {
+            assert_eq!(Some(value), expected.get(header), "{header}");
+        }
+        assert_eq!(headers.len(), expected.len() - 2);
+    }
+
+    #[test]
+    fn test_base32() {
+        let data = b"foobar";
+        assert_eq!(base32_encode(data), "MZXW6YTBOI");
+        assert_eq!(base32_decode("MZXW6YTBOI======").unwrap(), data);
+        assert!(base32_decode("not base32!").is_none());
+    }
+
+    #[test]
+    fn test_format() {
+        let headers = get_headers();
+        let serialized = serde_json::to_value(&headers).unwrap();
+        let expected: serde_json::Value = serde_json::from_str(
+            r#"{
+                 "warc-date":"2021-02-24T18:50:04Z",
+                 "warc-identified-content-language":"afr,eng",
+                 "content-type":"text/plain",
+                 "warc-record-id":"<urn:uuid:4c2d4cbb-24ef-4885-9516-d131fc15af2e>",
+                 "content-length":"4891",
+                 "warc-type":"conversion",
+                 "warc-refers-to":"<urn:uuid:94ff8c3f-838f-44e6-ba2a-a47262025819>",
+                 "warc-block-digest":"sha1:UMHO34U75QPBAT2DB756CLZYMVGQZC6G",
+                 "warc-target-uri":"https://www.foo.bar/baz?quux=1"
+             }"#,
+        )
+        .unwrap();
+        assert_eq!(serialized, expected);
+    }
+
+    #[test]
+    fn test_case() {
+        let headers: WarcHeaders = serde_json::from_str(
+            r#"{"WARC-Record-ID":"<urn:uuid:4c2d4cbb-24ef-4885-9516-d131fc15af2e>","X-Custom":"foo","warc-type":"conversion"}"#,
+        )
+        .unwrap();
+        assert!(headers.record_id().unwrap().is_some());
+        assert_eq!(
+            headers
+                .get_str(&WarcHeader::Unknown("x-custom".to_string()))
+                .unwrap(),
+            Some("foo")
+        );
+        let serialized = serde_json::to_value(&headers).unwrap();
+        assert_eq!(
+            serialized,
+            serde_json::json!({
+                "WARC-Record-ID": "<urn:uuid:4c2d4cbb-24ef-4885-9516-d131fc15af2e>",
+                "X-Custom": "foo",
+                "warc-type": "conversion"
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<WarcHeaders>(serialized).unwrap(),
+            headers
+        );
+    }
+}
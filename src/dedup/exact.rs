@@ -0,0 +1,298 @@
+//! Exact deduplication, at document and line level.
+//!
+//! Contents are compared through their 64-bit [hash](fn@super::hash), and two distinct contents with the same hash
+//! are considered duplicates. Collisions are unlikely but not impossible at corpus scale:
+//! the probability of at least one is about `n² / 2^65` for `n` distinct contents,
+//! around 3% for a billion documents or lines, and a third for four billions.
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::common::{CorpusDocument, DocumentLines};
+use crate::error::Error;
+
+use super::hash::hash_normalized;
+use super::store::HashStore;
+
+/// Removes documents whose normalized content has already been seen (see [super::normalize]).
+///
+/// ```
+/// use oscar_io::dedup::ExactDeduplicator;
+/// # use oscar_io::v3::Document;
+/// # let docs: Vec<Document> = vec![
+/// #     Document::builder("").build().unwrap(),
+/// #     Document::builder("").build().unwrap(),
+/// # ];
+///
+/// let unique: Vec<Document> = ExactDeduplicator::in_memory()
+///     .dedup(docs.into_iter().map(Ok))
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(unique.len(), 1);
+/// ```
+pub struct ExactDeduplicator<S> {
+    store: S,
+}
+
+impl ExactDeduplicator<HashMap<u64, u64>> {
+    /// Deduplicator keeping content hashes in memory.
+    pub fn in_memory() -> Self {
+        Self::new(HashMap::new())
+    }
+}
+
+impl<S: HashStore> ExactDeduplicator<S> {
+    /// Deduplicator keeping content hashes in `store` (use a [super::DiskHashCounter] for large corpora).
+    pub fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Checks if a document with the same normalized content has already been seen,
+    /// and remembers this one.
+    pub fn is_duplicate<D: CorpusDocument>(&mut self, doc: &D) -> Result<bool, Error> {
+        Ok(self.store.insert(hash_normalized(doc.content()))? > 1)
+    }
+
+    /// Deduplicate a stream of documents, keeping the first occurrence.
+    pub fn dedup<D, I>(self, documents: I) -> ExactDuplicates<S, I, D>
+    where
+        D: CorpusDocument,
+        I: Iterator<Item = Result<D, Error>>,
+    {
+        ExactDuplicates {
+            documents,
+            deduplicator: self,
+            document: PhantomData,
+        }
+    }
+}
+
+/// Iterator adapter returned by [ExactDeduplicator::dedup].
+pub struct ExactDuplicates<S, I, D> {
+    documents: I,
+    deduplicator: ExactDeduplicator<S>,
+    document: PhantomData<D>,
+}
+
+impl<S, I, D> Iterator for ExactDuplicates<S, I, D>
+where
+    S: HashStore,
+    D: CorpusDocument,
+    I: Iterator<Item = Result<D, Error>>,
+{
+    type Item = Result<D, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let doc = match self.documents.next()? {
+                Ok(doc) => doc,
+                Err(e) => return Some(Err(e)),
+            };
+            match self.deduplicator.is_duplicate(&doc) {
+                Ok(true) => continue,
+                Ok(false) => return Some(Ok(doc)),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Removes lines that are repeated across the corpus, such as boilerplate.
+///
+/// Works in two passes: lines are first counted over the whole corpus ([LineDeduplicator::count]),
+/// then lines that appear more than `max_occurrences` times are removed from every document
+/// ([LineDeduplicator::apply] or [LineDeduplicator::dedup]).
+/// Lines are compared after normalization (see [super::normalize]), and empty ones are ignored.
+pub struct LineDeduplicator<S> {
+    store: S,
+    max_occurrences: u64,
+}
+
+impl LineDeduplicator<HashMap<u64, u64>> {
+    /// Deduplicator keeping line counts in memory.
+    pub fn in_memory(max_occurrences: u64) -> Self {
+        Self::new(HashMap::new(), max_occurrences)
+    }
+}
+
+impl<S: HashStore> LineDeduplicator<S> {
+    /// Deduplicator keeping line counts in `store` (use a [super::DiskHashCounter] for large corpora).
+    pub fn new(store: S, max_occurrences: u64) -> Self {
+        Self {
+            store,
+            max_occurrences,
+        }
+    }
+
+    fn line_hash(line: &str) -> Option<u64> {
+        if line.trim().is_empty() {
+            None
+        } else {
+            Some(hash_normalized(line))
+        }
+    }
+
+    /// First pass: count the lines of a document.
+    pub fn count<D: CorpusDocument>(&mut self, doc: &D) -> Result<(), Error> {
+        for hash in doc.content().lines().filter_map(Self::line_hash) {
+            self.store.insert(hash)?;
+        }
+        Ok(())
+    }
+
+    /// First pass: count the lines of every document.
+    pub fn count_all<D, I>(&mut self, documents: I) -> Result<(), Error>
+    where
+        D: CorpusDocument,
+        I: IntoIterator<Item = Result<D, Error>>,
+    {
+        for doc in documents {
+            self.count(&doc?)?;
+        }
+        Ok(())
+    }
+
+    /// Second pass: remove frequent lines from a document, keeping sentence identifications aligned.
+    ///
+    /// Returns the number of removed lines.
+    pub fn apply<D: CorpusDocument>(&mut self, doc: &mut D) -> Result<usize, Error> {
+        let mut frequent = Vec::new();
+        for (idx, (line, _)) in doc.lines()?.enumerate() {
+            if let Some(hash) = Self::line_hash(line) {
+                if self.store.count(hash)? > self.max_occurrences {
+                    frequent.push(idx);
+                }
+            }
+        }
+        doc.drop_lines(&frequent)
+    }
+
+    /// Second pass over a stream of documents.
+    /// Documents that end up with no content are removed.
+    pub fn dedup<D, I>(self, documents: I) -> LineDuplicates<S, I, D>
+    where
+        D: CorpusDocument,
+        I: Iterator<Item = Result<D, Error>>,
+    {
+        LineDuplicates {
+            documents,
+            deduplicator: self,
+            document: PhantomData,
+        }
+    }
+}
+
+/// Iterator adapter returned by [LineDeduplicator::dedup].
+pub struct LineDuplicates<S, I, D> {
+    documents: I,
+    deduplicator: LineDeduplicator<S>,
+    document: PhantomData<D>,
+}
+
+impl<S, I, D> Iterator for LineDuplicates<S, I, D>
+where
+    S: HashStore,
+    D: CorpusDocument,
+    I: Iterator<Item = Result<D, Error>>,
+{
+    type Item = Result<D, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut doc = match self.documents.next()? {
+                Ok(doc) => doc,
+                Err(e) => return Some(Err(e)),
+            };
+            match self.deduplicator.apply(&mut doc) {
+                Ok(_) if doc.content().trim().is_empty() => continue,
+                Ok(_) => return Some(Ok(doc)),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use oxilangtag::LanguageTag;
+
+    use super::{ExactDeduplicator, LineDeduplicator};
+    use crate::common::CorpusDocument;
+    use crate::dedup::DiskHashCounter;
+    use crate::oscar_doc;
+    use crate::test_utils::doc;
+    use crate::v3::{self, Document, WriterTrait};
+
+    #[test]
+    fn test_documents() {
+        let docs = vec![
+            doc("Hello world\nfoo"),
+            doc("bar"),
+            doc("hello,  World!\nFoo"),
+            doc("bar"),
+        ];
+        let dir = tempfile::tempdir().unwrap();
+        let store = DiskHashCounter::create(&dir.path().join("hashes"), 0).unwrap();
+        let unique: Vec<Document> = ExactDeduplicator::new(store)
+            .dedup(docs.into_iter().map(Ok))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let contents: Vec<&str> = unique.iter().map(|d| d.content().as_str()).collect();
+        assert_eq!(contents, vec!["Hello world\nfoo", "bar"]);
+
+        // written through the usual writer
+        let mut writer = v3::Writer::new(
+            dir.path(),
+            LanguageTag::parse("en".to_string()).unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+        writer.write(unique).unwrap();
+        writer.flush().unwrap();
+        let written = std::fs::read_to_string(dir.path().join("en.jsonl")).unwrap();
+        assert_eq!(written.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_oscar_doc() {
+        let open = || {
+            let f = File::open("tests/res/data.jsonl").unwrap();
+            oscar_doc::Reader::new(BufReader::new(f))
+        };
+        let unique = ExactDeduplicator::in_memory().dedup(open()).count();
+        assert!(unique > 0);
+        let twice = ExactDeduplicator::in_memory()
+            .dedup(open().chain(open()))
+            .count();
+        assert_eq!(twice, unique);
+    }
+
+    #[test]
+    fn test_lines() {
+        let docs = vec![
+            doc("Cookie policy\nfirst\nShare this"),
+            doc("second\ncookie policy."),
+            doc("Cookie Policy\nShare this"),
+            doc("third\n\nshare this\nfourth"),
+        ];
+
+        let mut dedup = LineDeduplicator::in_memory(2);
+        dedup.count_all(docs.iter().cloned().map(Ok)).unwrap();
+        let cleaned: Vec<Document> = dedup
+            .dedup(docs.into_iter().map(Ok))
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        let contents: Vec<&str> = cleaned.iter().map(|d| d.content().as_str()).collect();
+        assert_eq!(contents, vec!["first", "second", "third\n\nfourth"]);
+        for doc in cleaned {
+            assert_eq!(
+                doc.content().lines().count(),
+                doc.sentence_identifications().len()
+            );
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Stable content hashing.
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a hash, with a final mixing step to spread bits.
+///
+/// Unlike [std::hash::Hash], it is stable across runs and platforms,
+/// so hashes can be stored on disk and compared between processes.
+pub fn hash(bytes: &[u8]) -> u64 {
+    let mut h = FNV_OFFSET;
+    for b in bytes {
+        h ^= *b as u64;
+        h = h.wrapping_mul(FNV_PRIME);
+    }
+    mix(h)
+}
+
+/// splitmix64 finalizer.
+pub(crate) fn mix(mut h: u64) -> u64 {
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    h ^ (h >> 31)
+}
+
+/// Normalizes text before hashing, so that trivial variations are considered as duplicates:
+/// text is lowercased, punctuation is removed and whitespace runs are collapsed into a single space.
+pub fn normalize(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    for word in text.split_whitespace() {
+        let mut word = word
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .peekable();
+        // skip words made only of punctuation
+        if word.peek().is_none() {
+            continue;
+        }
+        if !normalized.is_empty() {
+            normalized.push(' ');
+        }
+        normalized.extend(word);
+    }
+    normalized
+}
+
+/// Hash of the normalized text (see [normalize]).
+pub fn hash_normalized(text: &str) -> u64 {
+    hash(normalize(text).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash, hash_normalized, normalize};
+
+    #[test]
+    fn test_stable() {
+        // hashes are stored on disk, they must never change
+        assert_eq!(hash(b""), 0xf52a_15e9_a9b5_e89b);
+        assert_ne!(hash(b"foo"), hash(b"fop"));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("  Hello,   World !\n\tFOO-bar "),
+            "hello world foobar"
+        );
+        assert_eq!(normalize("..."), "");
+        assert_eq!(normalize("a - b"), "a b");
+        assert_eq!(
+            hash_normalized("Hello world"),
+            hash_normalized("hello, WORLD.")
+        );
+    }
+}
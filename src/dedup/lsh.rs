@@ -0,0 +1,363 @@
+//! LSH banding index, clustering near duplicates across a corpus.
+use std::collections::HashMap;
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::CorpusDocument;
+use crate::error::Error;
+
+use super::hash::hash;
+use super::minhash::{MinHasher, Signature};
+
+/// Clusters documents whose [Signature]s share at least one band.
+///
+/// Signatures are split into `bands` bands of `rows` values.
+/// Two documents with a Jaccard similarity of `s` share a band with probability `1 - (1 - s^rows)^bands`,
+/// so documents become likely to be clustered together around a similarity of `(1 / bands)^(1 / rows)`.
+///
+/// Clustering is transitive: if A is near B and B is near C, A, B and C end up in the same cluster.
+/// Documents are keyed by their WARC record id, so that indexing can span multiple files.
+///
+/// ```
+/// use oscar_io::dedup::{LshIndex, MinHasher};
+///
+/// let hasher = MinHasher::new(128, 3);
+/// let mut index = LshIndex::new(32, 4);
+/// let text = "the quick brown fox jumps over the lazy dog near the river bank";
+/// index.insert("a".to_string(), &hasher.signature(text)).unwrap();
+/// index.insert("b".to_string(), &hasher.signature(&text.to_uppercase())).unwrap();
+/// index.insert("c".to_string(), &hasher.signature("something else entirely")).unwrap();
+///
+/// let clusters = index.clusters();
+/// assert_eq!(clusters.representative("b"), Some("a"));
+/// assert!(clusters.is_representative("c"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LshIndex {
+    bands: usize,
+    rows: usize,
+    buckets: HashMap<(usize, u64), usize>,
+    ids: Vec<String>,
+    indices: HashMap<String, usize>,
+    parents: Vec<usize>,
+}
+
+impl LshIndex {
+    /// Index for signatures of `bands * rows` values.
+    pub fn new(bands: usize, rows: usize) -> Self {
+        Self {
+            bands,
+            rows,
+            buckets: HashMap::new(),
+            ids: Vec::new(),
+            indices: HashMap::new(),
+            parents: Vec::new(),
+        }
+    }
+
+    /// Number of indexed documents.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    fn root(&mut self, mut idx: usize) -> usize {
+        while self.parents[idx] != idx {
+            // path halving
+            self.parents[idx] = self.parents[self.parents[idx]];
+            idx = self.parents[idx];
+        }
+        idx
+    }
+
+    /// Merge clusters, the oldest root staying the root.
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.root(a), self.root(b));
+        if a != b {
+            let (root, child) = if a < b { (a, b) } else { (b, a) };
+            self.parents[child] = root;
+        }
+    }
+
+    /// Index a signature under `record_id`.
+    ///
+    /// Inserting a record id twice merges the clusters of both signatures.
+    /// Empty signatures (see [Signature::is_empty]) are not indexed, since they would all end up in the same cluster.
+    pub fn insert(&mut self, record_id: String, signature: &Signature) -> Result<(), Error> {
+        let values = signature.values();
+        if values.len() != self.bands * self.rows {
+            return Err(Error::Custom(format!(
+                "signature has {} values, expected {} ({} bands of {} rows)",
+                values.len(),
+                self.bands * self.rows,
+                self.bands,
+                self.rows
+            )));
+        }
+        if signature.is_empty() {
+            return Ok(());
+        }
+
+        let idx = match self.indices.get(&record_id) {
+            Some(idx) => *idx,
+            None => {
+                let idx = self.ids.len();
+                self.indices.insert(record_id.clone(), idx);
+                self.ids.push(record_id);
+                self.parents.push(idx);
+                idx
+            }
+        };
+
+        for (band, rows) in values.chunks_exact(self.rows).enumerate() {
+            let bytes: Vec<u8> = rows.iter().flat_map(|v| v.to_le_bytes()).collect();
+            let bucket = (band, hash(&bytes));
+            match self.buckets.get(&bucket) {
+                Some(&other) => self.union(idx, other),
+                None => {
+                    self.buckets.insert(bucket, idx);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Index a document, using its content and record id.
+    ///
+    /// Returns `false` if the document has no record id or no words (empty or whitespace-only content),
+    /// in which case it is not indexed.
+    pub fn insert_document<D: CorpusDocument>(
+        &mut self,
+        hasher: &MinHasher,
+        doc: &D,
+    ) -> Result<bool, Error> {
+        let Some(id) = doc.record_id() else {
+            return Ok(false);
+        };
+        let signature = hasher.signature(doc.content());
+        if signature.is_empty() {
+            return Ok(false);
+        }
+        self.insert(id.into_owned(), &signature)?;
+        Ok(true)
+    }
+
+    /// Index a stream of documents (see [LshIndex::insert_document]).
+    ///
+    /// Can be called once per file of a corpus.
+    pub fn insert_all<D, I>(&mut self, hasher: &MinHasher, documents: I) -> Result<(), Error>
+    where
+        D: CorpusDocument,
+        I: IntoIterator<Item = Result<D, Error>>,
+    {
+        for doc in documents {
+            self.insert_document(hasher, &doc?)?;
+        }
+        Ok(())
+    }
+
+    /// Compute cluster assignments.
+    ///
+    /// Each cluster is represented by its first indexed document.
+    pub fn clusters(&mut self) -> Clusters {
+        let roots: Vec<usize> = (0..self.ids.len()).map(|idx| self.root(idx)).collect();
+        let mut cluster_ids = HashMap::new();
+        let mut representatives = Vec::new();
+        let mut assignments = HashMap::with_capacity(self.ids.len());
+        for (id, root) in self.ids.iter().zip(roots) {
+            let cluster = *cluster_ids.entry(root).or_insert_with(|| {
+                representatives.push(self.ids[root].clone());
+                representatives.len() - 1
+            });
+            assignments.insert(id.clone(), cluster);
+        }
+        Clusters {
+            assignments,
+            representatives,
+        }
+    }
+}
+
+/// Cluster of a document, as written by [Clusters::write_jsonl].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClusterAssignment {
+    pub record_id: String,
+    pub cluster: usize,
+    /// Record id of the document representing the cluster.
+    pub representative: String,
+}
+
+/// Cluster assignments, keyed by record id.
+#[derive(Debug, Clone, Default)]
+pub struct Clusters {
+    assignments: HashMap<String, usize>,
+    representatives: Vec<String>,
+}
+
+impl Clusters {
+    /// Number of clusters.
+    pub fn len(&self) -> usize {
+        self.representatives.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.representatives.is_empty()
+    }
+
+    /// Get the cluster of a record id.
+    pub fn cluster(&self, record_id: &str) -> Option<usize> {
+        self.assignments.get(record_id).copied()
+    }
+
+    /// Get the record id representing the cluster of a record id.
+    pub fn representative(&self, record_id: &str) -> Option<&str> {
+        self.cluster(record_id)
+            .map(|cluster| self.representatives[cluster].as_str())
+    }
+
+    /// Checks if a record id represents its cluster.
+    /// Unknown record ids are considered as representatives.
+    pub fn is_representative(&self, record_id: &str) -> bool {
+        self.representative(record_id)
+            .is_none_or(|representative| representative == record_id)
+    }
+
+    /// Checks if a document should be kept when keeping one document per cluster.
+    /// Documents without record id are kept.
+    pub fn keep<D: CorpusDocument>(&self, doc: &D) -> bool {
+        doc.record_id().is_none_or(|id| self.is_representative(&id))
+    }
+
+    /// Iterate over assignments, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = ClusterAssignment> + '_ {
+        self.assignments
+            .iter()
+            .map(|(record_id, cluster)| ClusterAssignment {
+                record_id: record_id.clone(),
+                cluster: *cluster,
+                representative: self.representatives[*cluster].clone(),
+            })
+    }
+
+    /// Write assignments as JSON lines, sorted by cluster and record id.
+    pub fn write_jsonl<W: Write>(&self, mut w: W) -> Result<(), Error> {
+        let mut assignments: Vec<ClusterAssignment> = self.iter().collect();
+        assignments.sort_by(|a, b| (a.cluster, &a.record_id).cmp(&(b.cluster, &b.record_id)));
+        for assignment in assignments {
+            serde_json::to_writer(&mut w, &assignment)?;
+            w.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use oxilangtag::LanguageTag;
+
+    use super::{ClusterAssignment, LshIndex};
+    use crate::common::CorpusDocument;
+    use crate::dedup::MinHasher;
+    use crate::test_utils::doc;
+    use crate::v3::{self, Document, WriterTrait};
+
+    const TEXT: &str = "The quick brown fox jumps over the lazy dog near the river bank \
+        while the farmer watches from the old wooden fence and the sun sets slowly \
+        behind the hills, painting the sky with orange and purple colors.";
+
+    fn get_docs() -> Vec<Document> {
+        vec![
+            doc(TEXT),
+            doc(
+                "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor \
+            incididunt ut labore et dolore magna aliqua.",
+            ),
+            doc(TEXT.replace("lazy", "sleepy")),
+            doc(TEXT.to_lowercase()),
+        ]
+    }
+
+    #[test]
+    fn test_empty() {
+        let hasher = MinHasher::new(128, 3);
+        let mut index = LshIndex::new(32, 4);
+        for content in ["", " \n\t", TEXT] {
+            index.insert_document(&hasher, &doc(content)).unwrap();
+        }
+        assert_eq!(index.len(), 1);
+
+        let docs = [doc(""), doc("  ")];
+        for d in &docs {
+            assert!(!index.insert_document(&hasher, d).unwrap());
+        }
+        let clusters = index.clusters();
+        assert!(docs.iter().all(|d| clusters.keep(d)));
+        assert_eq!(clusters.len(), 1);
+    }
+
+    #[test]
+    fn test_clusters() {
+        let docs = get_docs();
+        let hasher = MinHasher::new(128, 3);
+        let mut index = LshIndex::new(32, 4);
+        // two "files"
+        index
+            .insert_all(&hasher, docs[..2].iter().cloned().map(Ok))
+            .unwrap();
+        index
+            .insert_all(&hasher, docs[2..].iter().cloned().map(Ok))
+            .unwrap();
+        assert_eq!(index.len(), 4);
+
+        let clusters = index.clusters();
+        let ids: Vec<String> = docs
+            .iter()
+            .map(|d| d.record_id().unwrap().into_owned())
+            .collect();
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters.cluster(&ids[0]), Some(0));
+        assert_eq!(clusters.cluster(&ids[1]), Some(1));
+        assert_eq!(clusters.representative(&ids[2]), Some(ids[0].as_str()));
+        assert_eq!(clusters.representative(&ids[3]), Some(ids[0].as_str()));
+        assert!(clusters.is_representative("unknown"));
+
+        let mut out = Vec::new();
+        clusters.write_jsonl(&mut out).unwrap();
+        let written: Vec<ClusterAssignment> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(written.len(), 4);
+        assert!(written.iter().all(|a| a.representative == ids[a.cluster]));
+
+        // keep one representative per cluster
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = v3::Writer::new(
+            dir.path(),
+            LanguageTag::parse("en".to_string()).unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+        writer
+            .write(docs.into_iter().filter(|d| clusters.keep(d)).collect())
+            .unwrap();
+        writer.flush().unwrap();
+        let written = std::fs::read_to_string(dir.path().join("en.jsonl")).unwrap();
+        assert_eq!(written.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_invalid_signature() {
+        let hasher = MinHasher::new(10, 3);
+        let mut index = LshIndex::new(32, 4);
+        assert!(index
+            .insert("a".to_string(), &hasher.signature(TEXT))
+            .is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! MinHash signatures over word n-gram shingles.
+use super::hash::{hash, mix, normalize};
+
+/// Computes [Signature]s of texts.
+///
+/// Texts are normalized (see [super::normalize]) and split into shingles of `shingle_size` words.
+/// Hasher parameters are deterministic: signatures computed by two hashers with the same
+/// parameters (in different processes, for example) can be compared.
+#[derive(Debug, Clone)]
+pub struct MinHasher {
+    shingle_size: usize,
+    seeds: Vec<u64>,
+}
+
+impl MinHasher {
+    /// Hasher producing signatures of `num_perm` values, using shingles of `shingle_size` words.
+    ///
+    /// # Panics
+    /// Panics if `num_perm` or `shingle_size` is 0.
+    pub fn new(num_perm: usize, shingle_size: usize) -> Self {
+        assert!(num_perm > 0, "num_perm must be positive");
+        assert!(shingle_size > 0, "shingle_size must be positive");
+        Self {
+            shingle_size,
+            seeds: (0..num_perm as u64).map(|i| mix(i + 1)).collect(),
+        }
+    }
+
+    /// Number of values in signatures.
+    pub fn num_perm(&self) -> usize {
+        self.seeds.len()
+    }
+
+    /// Compute the signature of `text`.
+    ///
+    /// Texts shorter than `shingle_size` words are considered as a single shingle.
+    pub fn signature(&self, text: &str) -> Signature {
+        let normalized = normalize(text);
+        let words: Vec<&str> = normalized.split(' ').filter(|w| !w.is_empty()).collect();
+        let mut values = vec![u64::MAX; self.seeds.len()];
+        if words.is_empty() {
+            return Signature { values };
+        }
+
+        let size = self.shingle_size.min(words.len());
+        for shingle in words.windows(size) {
+            let shingle_hash = hash(shingle.join(" ").as_bytes());
+            for (value, seed) in values.iter_mut().zip(self.seeds.iter()) {
+                *value = (*value).min(mix(shingle_hash ^ seed));
+            }
+        }
+        Signature { values }
+    }
+}
+
+/// MinHash signature of a text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    values: Vec<u64>,
+}
+
+impl Signature {
+    /// Get the signature values.
+    pub fn values(&self) -> &[u64] {
+        &self.values
+    }
+
+    /// Checks if the text had no words, in which case the signature is the same as every other empty text's.
+    pub fn is_empty(&self) -> bool {
+        self.values.iter().all(|v| *v == u64::MAX)
+    }
+
+    /// Estimate the Jaccard similarity between the shingle sets of two texts.
+    ///
+    /// Signatures of different sizes share no values.
+    pub fn jaccard(&self, other: &Self) -> f64 {
+        if self.values.len() != other.values.len() {
+            return 0.0;
+        }
+        let same = self
+            .values
+            .iter()
+            .zip(other.values.iter())
+            .filter(|(a, b)| a == b)
+            .count();
+        same as f64 / self.values.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MinHasher;
+
+    const TEXT: &str = "The quick brown fox jumps over the lazy dog near the river bank \
+        while the farmer watches from the old wooden fence and the sun sets slowly";
+
+    #[test]
+    fn test_jaccard() {
+        let hasher = MinHasher::new(128, 3);
+        let sig = hasher.signature(TEXT);
+        assert_eq!(sig.values().len(), 128);
+        assert_eq!(sig.jaccard(&hasher.signature(&TEXT.to_uppercase())), 1.0);
+
+        let near = hasher.signature(&TEXT.replace("lazy", "sleepy"));
+        let other = hasher.signature("Lorem ipsum dolor sit amet, consectetur adipiscing elit");
+        let (j_near, j_other) = (sig.jaccard(&near), sig.jaccard(&other));
+        assert!(j_near > 0.6, "{j_near}");
+        assert!(j_other < 0.1, "{j_other}");
+    }
+
+    #[test]
+    fn test_short() {
+        let hasher = MinHasher::new(16, 5);
+        assert_eq!(
+            hasher.signature("hello world"),
+            hasher.signature("Hello, world!")
+        );
+        assert_eq!(hasher.signature("").values(), &[u64::MAX; 16]);
+        assert_eq!(
+            MinHasher::new(16, 5).signature(TEXT),
+            hasher.signature(TEXT)
+        );
+    }
+}
@@ -0,0 +1,27 @@
+/*! Deduplication
+
+- [ExactDeduplicator] removes documents with the same (normalized) content,
+- [LineDeduplicator] removes lines that are repeated across a corpus,
+- [HashStore]s keep track of content hashes, either in memory or on disk ([DiskHashCounter]),
+- [MinHasher] computes MinHash [Signature]s, that an [LshIndex] clusters across a corpus,
+- [Tlsh] computes and compares [TLSH](https://github.com/trendmicro/tlsh) digests,
+- [TlshDeduplicator] detects near duplicates in a stream of [crate::v3::Document]s, and flags or removes them.
+
+!*/
+mod exact;
+mod hash;
+mod lsh;
+mod minhash;
+mod near_duplicates;
+mod store;
+mod tlsh;
+
+pub use exact::{ExactDeduplicator, ExactDuplicates, LineDeduplicator, LineDuplicates};
+pub use hash::{hash, hash_normalized, normalize};
+pub use lsh::{ClusterAssignment, Clusters, LshIndex};
+pub use minhash::{MinHasher, Signature};
+pub use near_duplicates::{
+    Action, NearDuplicate, NearDuplicates, TlshDeduplicator, NEAR_DUPLICATE_KEY,
+};
+pub use store::{DiskHashCounter, HashStore, DEFAULT_CACHE_SIZE};
+pub use tlsh::{document_tlsh, tlsh_distance, update_tlsh, Tlsh, MIN_DATA_LENGTH};
@@ -0,0 +1,241 @@
+//! Streaming TLSH-based near-duplicate detection.
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::v3::Document;
+
+use super::tlsh::{document_tlsh, Tlsh};
+
+/// Extension key where [NearDuplicate] is recorded when using [Action::Flag].
+pub const NEAR_DUPLICATE_KEY: &str = "near_duplicate";
+
+/// Number of code bytes per band. Two documents are compared only if they share a band.
+const BAND_SIZE: usize = 2;
+
+/// What to do with near duplicates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Keep the document, recording a [NearDuplicate] in its extensions.
+    Flag,
+    /// Remove the document from the stream.
+    Remove,
+}
+
+/// Closest previously seen document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NearDuplicate {
+    /// Record id of the previously seen document.
+    pub of: Option<String>,
+    /// TLSH distance to it.
+    pub distance: u32,
+}
+
+/// Detects near duplicates in a stream of documents, keeping the first occurrence.
+///
+/// Documents are compared using TLSH (from [crate::v3::Metadata::tlsh] if set, computed otherwise).
+/// To avoid comparing each document with every previous one, TLSH codes are split into bands,
+/// and only documents sharing at least one band are compared.
+/// This means that some near duplicates with a distance close to the threshold can be missed.
+///
+/// Documents that can't be hashed (too short, see [super::tlsh::MIN_DATA_LENGTH]) are always kept.
+///
+/// The digest, record id and bands of every kept document stay in memory (a few hundred bytes per document)
+/// and there is no bound: split large corpora (by language, for example) before deduplicating them.
+pub struct TlshDeduplicator {
+    threshold: u32,
+    action: Action,
+    seen: Vec<(Tlsh, Option<String>)>,
+    bands: HashMap<(usize, [u8; BAND_SIZE]), Vec<usize>>,
+}
+
+impl TlshDeduplicator {
+    /// Documents at a distance of `threshold` or less of a previous one are near duplicates.
+    pub fn new(threshold: u32, action: Action) -> Self {
+        Self {
+            threshold,
+            action,
+            seen: Vec::new(),
+            bands: HashMap::new(),
+        }
+    }
+
+    fn bands(tlsh: &Tlsh) -> impl Iterator<Item = (usize, [u8; BAND_SIZE])> + '_ {
+        tlsh.code()
+            .chunks_exact(BAND_SIZE)
+            .enumerate()
+            .map(|(idx, band)| (idx, [band[0], band[1]]))
+    }
+
+    /// Check a document against the previous ones.
+    ///
+    /// Returns the closest previous document within the threshold, if any.
+    /// Otherwise, the document is remembered for the next checks.
+    pub fn check(&mut self, doc: &Document) -> Result<Option<NearDuplicate>, Error> {
+        let tlsh = match document_tlsh(doc)? {
+            Some(tlsh) => tlsh,
+            None => return Ok(None),
+        };
+
+        let mut candidates: Vec<usize> = Self::bands(&tlsh)
+            .filter_map(|band| self.bands.get(&band))
+            .flatten()
+            .copied()
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let closest = candidates
+            .into_iter()
+            .map(|idx| (idx, self.seen[idx].0.distance(&tlsh)))
+            .filter(|(_, distance)| *distance <= self.threshold)
+            .min_by_key(|(idx, distance)| (*distance, *idx));
+
+        if let Some((idx, distance)) = closest {
+            return Ok(Some(NearDuplicate {
+                of: self.seen[idx].1.clone(),
+                distance,
+            }));
+        }
+
+        let idx = self.seen.len();
+        for band in Self::bands(&tlsh) {
+            self.bands.entry(band).or_default().push(idx);
+        }
+        self.seen
+            .push((tlsh, doc.warc_id().map(|id| id.into_owned())));
+        Ok(None)
+    }
+
+    /// Deduplicate a stream of documents.
+    pub fn dedup<I>(self, documents: I) -> NearDuplicates<I>
+    where
+        I: Iterator<Item = Result<Document, Error>>,
+    {
+        NearDuplicates {
+            documents,
+            deduplicator: self,
+        }
+    }
+}
+
+/// Iterator adapter returned by [TlshDeduplicator::dedup].
+pub struct NearDuplicates<I> {
+    documents: I,
+    deduplicator: TlshDeduplicator,
+}
+
+impl<I> Iterator for NearDuplicates<I>
+where
+    I: Iterator<Item = Result<Document, Error>>,
+{
+    type Item = Result<Document, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut doc = match self.documents.next()? {
+                Ok(doc) => doc,
+                Err(e) => return Some(Err(e)),
+            };
+            let near_duplicate = match self.deduplicator.check(&doc) {
+                Ok(nd) => nd,
+                Err(e) => return Some(Err(e)),
+            };
+            match (near_duplicate, self.deduplicator.action) {
+                (None, _) => return Some(Ok(doc)),
+                (Some(_), Action::Remove) => continue,
+                (Some(nd), Action::Flag) => {
+                    let flagged = doc
+                        .metadata_mut()
+                        .set_extension(NEAR_DUPLICATE_KEY, &nd)
+                        .map(|_| doc);
+                    return Some(flagged);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, NearDuplicate, TlshDeduplicator, NEAR_DUPLICATE_KEY};
+    use crate::dedup::{tlsh_distance, update_tlsh};
+    use crate::test_utils::doc;
+    use crate::v3::Document;
+
+    const TEXT: &str = "The quick brown fox jumps over the lazy dog. \
+        Pack my box with five dozen liquor jugs. \
+        How vexingly quick daft zebras jump! \
+        Sphinx of black quartz, judge my vow. \
+        The five boxing wizards jump quickly.";
+
+    fn get_docs() -> Vec<Document> {
+        [
+            TEXT.to_string(),
+            "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor \
+            incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud \
+            exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat."
+                .to_string(),
+            TEXT.to_string(),
+            TEXT.replace("lazy", "sleepy"),
+            "short".to_string(),
+            "short".to_string(),
+        ]
+        .into_iter()
+        .map(doc)
+        .collect()
+    }
+
+    #[test]
+    fn test_remove() {
+        let docs = get_docs();
+        let dedup = TlshDeduplicator::new(30, Action::Remove);
+        let kept: Vec<Document> = dedup
+            .dedup(docs.clone().into_iter().map(Ok))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let contents: Vec<&str> = kept.iter().map(|d| d.content().as_str()).collect();
+        assert_eq!(
+            contents,
+            vec![
+                docs[0].content().as_str(),
+                docs[1].content(),
+                "short",
+                "short"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flag() {
+        let mut docs = get_docs();
+        update_tlsh(&mut docs[0]);
+        let dedup = TlshDeduplicator::new(30, Action::Flag);
+        let flagged: Vec<Option<NearDuplicate>> = dedup
+            .dedup(docs.clone().into_iter().map(Ok))
+            .map(|doc| {
+                doc.unwrap()
+                    .metadata()
+                    .get_extension(NEAR_DUPLICATE_KEY)
+                    .unwrap()
+            })
+            .collect();
+
+        assert_eq!(flagged.len(), docs.len());
+        assert_eq!(flagged[0], None);
+        assert_eq!(flagged[1], None);
+        let exact = flagged[2].as_ref().unwrap();
+        assert_eq!(exact.distance, 0);
+        assert_eq!(exact.of.as_deref(), docs[0].warc_id().as_deref());
+        assert!(flagged[3].as_ref().unwrap().distance <= 30);
+    }
+
+    #[test]
+    fn test_document_distance() {
+        let docs = get_docs();
+        assert_eq!(tlsh_distance(&docs[0], &docs[2]).unwrap(), Some(0));
+        assert!(tlsh_distance(&docs[0], &docs[3]).unwrap().unwrap() <= 30);
+        assert_eq!(tlsh_distance(&docs[0], &docs[4]).unwrap(), None);
+    }
+}
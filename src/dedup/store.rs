@@ -0,0 +1,279 @@
+//! Hash stores, counting occurrences of hashes either in memory or on disk.
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+
+/// Counts occurrences of hashes.
+pub trait HashStore {
+    /// Record an occurrence of `hash`, and return its number of occurrences (including this one).
+    fn insert(&mut self, hash: u64) -> Result<u64, Error>;
+
+    /// Get the number of occurrences of `hash`.
+    fn count(&mut self, hash: u64) -> Result<u64, Error>;
+}
+
+/// In-memory store.
+impl HashStore for HashMap<u64, u64> {
+    fn insert(&mut self, hash: u64) -> Result<u64, Error> {
+        let count = self.entry(hash).or_insert(0);
+        *count += 1;
+        Ok(*count)
+    }
+
+    fn count(&mut self, hash: u64) -> Result<u64, Error> {
+        Ok(self.get(&hash).copied().unwrap_or(0))
+    }
+}
+
+/// Size of a slot: hash and count, little endian.
+const SLOT_SIZE: u64 = 16;
+const MIN_CAPACITY: u64 = 1024;
+/// Number of slots per cached page (64 KiB).
+const PAGE_SLOTS: u64 = 4096;
+/// Default size of the page cache (16 MiB).
+pub const DEFAULT_CACHE_SIZE: u64 = 256 * PAGE_SLOTS * SLOT_SIZE;
+
+#[derive(Debug)]
+struct Page {
+    data: Vec<u8>,
+    dirty: bool,
+}
+
+/// Open addressing hash table stored in a file, read and written through a page cache.
+///
+/// Key 0 marks empty slots.
+#[derive(Debug)]
+struct Table {
+    file: File,
+    capacity: u64,
+    max_pages: usize,
+    pages: HashMap<u64, Page>,
+    // loaded pages, evicted in loading order
+    order: VecDeque<u64>,
+}
+
+impl Table {
+    fn create(path: &Path, capacity: u64, max_pages: usize) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(capacity * SLOT_SIZE)?;
+        Ok(Self {
+            file,
+            capacity,
+            max_pages,
+            pages: HashMap::new(),
+            order: VecDeque::new(),
+        })
+    }
+
+    fn page(&mut self, page: u64) -> Result<&mut Page, Error> {
+        if !self.pages.contains_key(&page) {
+            if self.pages.len() >= self.max_pages {
+                self.evict()?;
+            }
+            let start = page * PAGE_SLOTS;
+            let slots = PAGE_SLOTS.min(self.capacity - start);
+            let mut data = vec![0u8; (slots * SLOT_SIZE) as usize];
+            self.file.seek(SeekFrom::Start(start * SLOT_SIZE))?;
+            self.file.read_exact(&mut data)?;
+            self.pages.insert(page, Page { data, dirty: false });
+            self.order.push_back(page);
+        }
+        Ok(self.pages.get_mut(&page).expect("page is loaded"))
+    }
+
+    /// Remove the oldest page from the cache, writing it back if needed.
+    fn evict(&mut self) -> Result<(), Error> {
+        if let Some(idx) = self.order.pop_front() {
+            let page = self.pages.remove(&idx).expect("pages in order are loaded");
+            if page.dirty {
+                self.file
+                    .seek(SeekFrom::Start(idx * PAGE_SLOTS * SLOT_SIZE))?;
+                self.file.write_all(&page.data)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_slot(&mut self, idx: u64) -> Result<(u64, u64), Error> {
+        let offset = ((idx % PAGE_SLOTS) * SLOT_SIZE) as usize;
+        let page = self.page(idx / PAGE_SLOTS)?;
+        let (key, count) = page.data[offset..offset + SLOT_SIZE as usize].split_at(8);
+        Ok((
+            u64::from_le_bytes(key.try_into().unwrap()),
+            u64::from_le_bytes(count.try_into().unwrap()),
+        ))
+    }
+
+    fn write_slot(&mut self, idx: u64, key: u64, count: u64) -> Result<(), Error> {
+        let offset = ((idx % PAGE_SLOTS) * SLOT_SIZE) as usize;
+        let page = self.page(idx / PAGE_SLOTS)?;
+        page.data[offset..offset + 8].copy_from_slice(&key.to_le_bytes());
+        page.data[offset + 8..offset + SLOT_SIZE as usize].copy_from_slice(&count.to_le_bytes());
+        page.dirty = true;
+        Ok(())
+    }
+
+    /// Find the slot holding `key`, or the empty slot where it should go.
+    fn find(&mut self, key: u64) -> Result<(u64, u64, u64), Error> {
+        let mut idx = key & (self.capacity - 1);
+        loop {
+            let (slot_key, count) = self.read_slot(idx)?;
+            if slot_key == key || slot_key == 0 {
+                return Ok((idx, slot_key, count));
+            }
+            idx = (idx + 1) & (self.capacity - 1);
+        }
+    }
+}
+
+/// File-backed open addressing hash table counting hashes, for corpora whose hashes don't fit in memory.
+///
+/// The table doubles in size (and is rewritten) when it is half full.
+/// It is accessed through a cache of [DEFAULT_CACHE_SIZE] bytes (see [DiskHashCounter::with_cache_size]).
+/// The backing file is a scratch file: it is removed when the store is dropped.
+#[derive(Debug)]
+pub struct DiskHashCounter {
+    path: PathBuf,
+    table: Table,
+    len: u64,
+    // 0 marks empty slots, so the count of hash 0 is kept apart
+    zero: u64,
+}
+
+impl DiskHashCounter {
+    /// Create a store backed by `path`, overwriting it if it exists.
+    ///
+    /// `capacity` is rounded up to a power of two.
+    pub fn create(path: &Path, capacity: u64) -> Result<Self, Error> {
+        let capacity = capacity.max(MIN_CAPACITY).next_power_of_two();
+        Ok(Self {
+            path: path.to_path_buf(),
+            table: Table::create(path, capacity, Self::cache_pages(DEFAULT_CACHE_SIZE))?,
+            len: 0,
+            zero: 0,
+        })
+    }
+
+    /// Set the size of the page cache, in bytes (at least one 64 KiB page).
+    pub fn with_cache_size(mut self, bytes: u64) -> Self {
+        self.table.max_pages = Self::cache_pages(bytes);
+        self
+    }
+
+    fn cache_pages(bytes: u64) -> usize {
+        (bytes / (PAGE_SLOTS * SLOT_SIZE)).max(1) as usize
+    }
+
+    /// Number of distinct hashes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Double the capacity, rehashing every entry in a new file.
+    fn grow(&mut self) -> Result<(), Error> {
+        let tmp_path = self.path.with_extension("grow");
+        let mut table = Table::create(&tmp_path, self.table.capacity * 2, self.table.max_pages)?;
+        for idx in 0..self.table.capacity {
+            let (key, count) = self.table.read_slot(idx)?;
+            if key != 0 {
+                let (new_idx, _, _) = table.find(key)?;
+                table.write_slot(new_idx, key, count)?;
+            }
+        }
+        // the old file has to be closed before being replaced, which fails otherwise on Windows
+        drop(std::mem::replace(&mut self.table, table));
+        std::fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+impl HashStore for DiskHashCounter {
+    fn insert(&mut self, hash: u64) -> Result<u64, Error> {
+        if hash == 0 {
+            self.zero += 1;
+            if self.zero == 1 {
+                self.len += 1;
+            }
+            return Ok(self.zero);
+        }
+        if (self.len + 1) * 2 > self.table.capacity {
+            self.grow()?;
+        }
+        let (idx, slot_key, count) = self.table.find(hash)?;
+        if slot_key == 0 {
+            self.len += 1;
+        }
+        self.table.write_slot(idx, hash, count + 1)?;
+        Ok(count + 1)
+    }
+
+    fn count(&mut self, hash: u64) -> Result<u64, Error> {
+        if hash == 0 {
+            return Ok(self.zero);
+        }
+        let (_, _, count) = self.table.find(hash)?;
+        Ok(count)
+    }
+}
+
+impl Drop for DiskHashCounter {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log::warn!("could not remove {:?}: {e}", self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{DiskHashCounter, HashStore};
+
+    fn check_store<S: HashStore>(store: &mut S) {
+        for i in 0..3000u64 {
+            assert_eq!(store.insert(i * 7919).unwrap(), 1);
+        }
+        assert_eq!(store.insert(0).unwrap(), 2);
+        assert_eq!(store.count(1).unwrap(), 0);
+        assert_eq!(store.insert(1).unwrap(), 1);
+        assert_eq!(store.insert(42 * 7919).unwrap(), 2);
+        assert_eq!(store.count(42 * 7919).unwrap(), 2);
+        assert_eq!(store.count(5).unwrap(), 0);
+        assert_eq!(store.count(2999 * 7919).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_memory() {
+        check_store(&mut HashMap::new());
+    }
+
+    #[test]
+    fn test_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hashes");
+        // a single cached page, smaller than the table once grown
+        let mut store = DiskHashCounter::create(&path, 10)
+            .unwrap()
+            .with_cache_size(0);
+        check_store(&mut store);
+        // grown three times, from 1024 to 8192 slots
+        assert_eq!(store.len(), 3001);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 8192 * 16);
+
+        std::mem::drop(store);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,341 @@
+//! [TLSH](https://github.com/trendmicro/tlsh) locality sensitive hashing (128 buckets, 1 byte checksum).
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::error::Error;
+use crate::v3::Document;
+
+/// Pearson hashing permutation table used by TLSH.
+#[rustfmt::skip]
+const V_TABLE: [u8; 256] = [
+    1, 87, 49, 12, 176, 178, 102, 166, 121, 193, 6, 84, 249, 230, 44, 163,
+    14, 197, 213, 181, 161, 85, 218, 80, 64, 239, 24, 226, 236, 142, 38, 200,
+    110, 177, 104, 103, 141, 253, 255, 50, 77, 101, 81, 18, 45, 96, 31, 222,
+    25, 107, 190, 70, 86, 237, 240, 34, 72, 242, 20, 214, 244, 227, 149, 235,
+    97, 234, 57, 22, 60, 250, 82, 175, 208, 5, 127, 199, 111, 62, 135, 248,
+    174, 169, 211, 58, 66, 154, 106, 195, 245, 171, 17, 187, 182, 179, 0, 243,
+    132, 56, 148, 75, 128, 133, 158, 100, 130, 126, 91, 13, 153, 246, 216, 219,
+    119, 68, 223, 78, 83, 88, 201, 99, 122, 11, 92, 32, 136, 114, 52, 10,
+    138, 30, 48, 183, 156, 35, 61, 26, 143, 74, 251, 94, 129, 162, 63, 152,
+    170, 7, 115, 167, 241, 206, 3, 150, 55, 59, 151, 220, 90, 53, 23, 131,
+    125, 173, 15, 238, 79, 95, 89, 16, 105, 137, 225, 224, 217, 160, 37, 123,
+    118, 73, 2, 157, 46, 116, 9, 145, 134, 228, 207, 212, 202, 215, 69, 229,
+    27, 188, 67, 124, 168, 252, 42, 4, 29, 108, 21, 247, 19, 205, 39, 203,
+    233, 40, 186, 147, 198, 192, 155, 33, 164, 191, 98, 204, 165, 180, 117, 76,
+    140, 36, 210, 172, 41, 54, 159, 8, 185, 232, 113, 196, 231, 47, 146, 120,
+    51, 65, 28, 144, 254, 221, 93, 189, 194, 139, 112, 43, 71, 109, 184, 209,
+];
+
+const BUCKETS: usize = 128;
+/// Number of bytes of the bucket code (2 bits per bucket).
+pub(crate) const CODE_SIZE: usize = BUCKETS / 4;
+/// Inputs shorter than that can't be hashed.
+pub const MIN_DATA_LENGTH: usize = 50;
+const WINDOW_SIZE: usize = 5;
+/// Hashes are prefixed by their version.
+const VERSION_PREFIX: &str = "T1";
+
+fn b_mapping(salt: u8, i: u8, j: u8, k: u8) -> u8 {
+    let mut h = V_TABLE[salt as usize];
+    h = V_TABLE[(h ^ i) as usize];
+    h = V_TABLE[(h ^ j) as usize];
+    V_TABLE[(h ^ k) as usize]
+}
+
+/// Log-scale length capture.
+fn l_capturing(len: usize) -> u8 {
+    let len = len as f64;
+    let i = if len <= 656.0 {
+        (len.ln() / 0.4054651).floor()
+    } else if len <= 3199.0 {
+        (len.ln() / 0.26236426 - 8.72777).floor()
+    } else {
+        (len.ln() / 0.095310180 - 62.5472).floor()
+    };
+    (i as i64 & 0xFF) as u8
+}
+
+fn swap_byte(b: u8) -> u8 {
+    b.rotate_left(4)
+}
+
+/// Distance between two values on a circular range.
+fn mod_diff(x: u8, y: u8, range: u32) -> u32 {
+    let (x, y) = (x as u32, y as u32);
+    let (dl, dr) = if y > x {
+        (y - x, x + range - y)
+    } else {
+        (x - y, y + range - x)
+    };
+    dl.min(dr)
+}
+
+/// Distance between two bucket codes: each 2-bit pair difference counts, with 6 for maximal ones.
+fn code_distance(a: &[u8; CODE_SIZE], b: &[u8; CODE_SIZE]) -> u32 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| {
+            (0..4)
+                .map(|shift| {
+                    let d = ((x >> (shift * 2)) & 3).abs_diff((y >> (shift * 2)) & 3);
+                    if d == 3 {
+                        6
+                    } else {
+                        d as u32
+                    }
+                })
+                .sum::<u32>()
+        })
+        .sum()
+}
+
+/// A TLSH digest.
+///
+/// Displays and parses as the usual `T1`-prefixed hex string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tlsh {
+    checksum: u8,
+    lvalue: u8,
+    q1_ratio: u8,
+    q2_ratio: u8,
+    code: [u8; CODE_SIZE],
+}
+
+impl Tlsh {
+    /// Hash `data`.
+    ///
+    /// Returns [None] if `data` is shorter than [MIN_DATA_LENGTH] or doesn't have enough variety,
+    /// in which case TLSH is not meaningful.
+    pub fn hash(data: &[u8]) -> Option<Self> {
+        if data.len() < MIN_DATA_LENGTH {
+            return None;
+        }
+
+        let mut buckets = [0u32; 256];
+        let mut checksum = 0u8;
+        for window in data.windows(WINDOW_SIZE) {
+            // window[4] is the most recent byte
+            let (a, b, c, d, e) = (window[4], window[3], window[2], window[1], window[0]);
+            checksum = b_mapping(0, a, b, checksum);
+            buckets[b_mapping(2, a, b, c) as usize] += 1;
+            buckets[b_mapping(3, a, b, d) as usize] += 1;
+            buckets[b_mapping(5, a, c, d) as usize] += 1;
+            buckets[b_mapping(7, a, c, e) as usize] += 1;
+            buckets[b_mapping(11, a, b, e) as usize] += 1;
+            buckets[b_mapping(13, a, d, e) as usize] += 1;
+        }
+        let buckets = &buckets[..BUCKETS];
+
+        let nonzero = buckets.iter().filter(|&&b| b > 0).count();
+        if nonzero <= BUCKETS / 2 {
+            return None;
+        }
+
+        let mut sorted = buckets.to_vec();
+        sorted.sort_unstable();
+        let (q1, q2, q3) = (
+            sorted[BUCKETS / 4 - 1],
+            sorted[BUCKETS / 2 - 1],
+            sorted[BUCKETS - BUCKETS / 4 - 1],
+        );
+        if q3 == 0 {
+            return None;
+        }
+
+        let mut code = [0u8; CODE_SIZE];
+        for (i, byte) in code.iter_mut().enumerate() {
+            for j in 0..4 {
+                let k = buckets[4 * i + j];
+                let quartile = if q3 < k {
+                    3
+                } else if q2 < k {
+                    2
+                } else if q1 < k {
+                    1
+                } else {
+                    0
+                };
+                *byte += quartile << (j * 2);
+            }
+        }
+
+        Some(Self {
+            checksum,
+            lvalue: l_capturing(data.len()),
+            q1_ratio: ((q1 as u64 * 100 / q3 as u64) % 16) as u8,
+            q2_ratio: ((q2 as u64 * 100 / q3 as u64) % 16) as u8,
+            code,
+        })
+    }
+
+    /// Distance between two digests, taking length into account.
+    /// 0 means (nearly) identical, and values under ~50 usually indicate near duplicates.
+    pub fn distance(&self, other: &Self) -> u32 {
+        let mut diff = match mod_diff(self.lvalue, other.lvalue, 256) {
+            d @ (0 | 1) => d,
+            d => d * 12,
+        };
+        for (a, b) in [
+            (self.q1_ratio, other.q1_ratio),
+            (self.q2_ratio, other.q2_ratio),
+        ] {
+            diff += match mod_diff(a, b, 16) {
+                d @ (0 | 1) => d,
+                d => (d - 1) * 12,
+            };
+        }
+        if self.checksum != other.checksum {
+            diff += 1;
+        }
+        diff + code_distance(&self.code, &other.code)
+    }
+
+    /// Get the bucket code (2 bits per bucket).
+    pub(crate) fn code(&self) -> &[u8; CODE_SIZE] {
+        &self.code
+    }
+}
+
+impl Display for Tlsh {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{VERSION_PREFIX}{:02X}{:02X}{:02X}",
+            swap_byte(self.checksum),
+            swap_byte(self.lvalue),
+            swap_byte((self.q2_ratio << 4) | self.q1_ratio),
+        )?;
+        for byte in self.code.iter().rev() {
+            write!(f, "{byte:02X}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Tlsh {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::Custom(format!("invalid TLSH digest: {s}"));
+        let hex = s.strip_prefix(VERSION_PREFIX).unwrap_or(s);
+        if hex.len() != 2 * (3 + CODE_SIZE) || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid()))
+            .collect::<Result<Vec<u8>, Error>>()?;
+
+        let q = swap_byte(bytes[2]);
+        let mut code = [0u8; CODE_SIZE];
+        for (byte, parsed) in code.iter_mut().zip(bytes[3..].iter().rev()) {
+            *byte = *parsed;
+        }
+        Ok(Self {
+            checksum: swap_byte(bytes[0]),
+            lvalue: swap_byte(bytes[1]),
+            q1_ratio: q & 0x0F,
+            q2_ratio: q >> 4,
+            code,
+        })
+    }
+}
+
+/// Get the TLSH digest of a document's content, from [crate::v3::Metadata::tlsh] if set, computed otherwise.
+///
+/// Returns [None] if the content is too short to be hashed, and fails if the stored digest is malformed.
+pub fn document_tlsh(doc: &Document) -> Result<Option<Tlsh>, Error> {
+    match doc.metadata().tlsh() {
+        Some(tlsh) => Ok(Some(tlsh.parse()?)),
+        None => Ok(Tlsh::hash(doc.content().as_bytes())),
+    }
+}
+
+/// Compute the TLSH digest of a document's content and store it in [crate::v3::Metadata::tlsh].
+pub fn update_tlsh(doc: &mut Document) -> Option<&String> {
+    let tlsh = Tlsh::hash(doc.content().as_bytes()).map(|tlsh| tlsh.to_string());
+    doc.metadata_mut().set_tlsh(tlsh);
+    doc.metadata().tlsh()
+}
+
+/// TLSH distance between two documents (see [Tlsh::distance]).
+///
+/// Returns [None] if either document can't be hashed.
+pub fn tlsh_distance(a: &Document, b: &Document) -> Result<Option<u32>, Error> {
+    match (document_tlsh(a)?, document_tlsh(b)?) {
+        (Some(a), Some(b)) => Ok(Some(a.distance(&b))),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Tlsh, V_TABLE};
+
+    const TEXT: &str = "The quick brown fox jumps over the lazy dog. \
+        Pack my box with five dozen liquor jugs. \
+        How vexingly quick daft zebras jump! \
+        Sphinx of black quartz, judge my vow. \
+        The five boxing wizards jump quickly.";
+
+    #[test]
+    fn test_table_is_permutation() {
+        let mut sorted = V_TABLE.to_vec();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..=255).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn test_too_short() {
+        assert_eq!(Tlsh::hash(b"foo"), None);
+        assert_eq!(Tlsh::hash(&[b'a'; 1000]), None);
+    }
+
+    #[test]
+    fn test_known_digests() {
+        // digests from a line-by-line port of the reference tlsh_impl.cpp
+        assert_eq!(
+            Tlsh::hash(TEXT.as_bytes()).unwrap().to_string(),
+            "T129D023C4F658019506E9040C435A75B3D1ECCA045313F63051745143205C1734CF06B5"
+        );
+        // long enough to use another length capture range
+        let long: String = (0..5).map(|i| format!("{i}: {TEXT}\n")).collect();
+        assert_eq!(long.len(), 1010);
+        assert_eq!(
+            Tlsh::hash(long.as_bytes()).unwrap().to_string(),
+            "T1C71100C4FA5802D50AE9084D435EB5B3D1ECCE089323FA3051745153205C1738CF46B5"
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let hash = Tlsh::hash(TEXT.as_bytes()).unwrap();
+        let s = hash.to_string();
+        assert_eq!(s.len(), 72);
+        assert!(s.starts_with("T1"));
+        let parsed: Tlsh = s.parse().unwrap();
+        assert_eq!(parsed, hash);
+        assert_eq!(hash.distance(&parsed), 0);
+
+        assert!("T1ABC".parse::<Tlsh>().is_err());
+        assert!(format!("T1{}", "G".repeat(70)).parse::<Tlsh>().is_err());
+    }
+
+    #[test]
+    fn test_distance() {
+        let hash = Tlsh::hash(TEXT.as_bytes()).unwrap();
+        let near = Tlsh::hash(TEXT.replace("lazy", "sleepy").as_bytes()).unwrap();
+        let other = Tlsh::hash(
+            "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor \
+            incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud \
+            exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat."
+                .as_bytes(),
+        )
+        .unwrap();
+
+        let d_near = hash.distance(&near);
+        let d_other = hash.distance(&other);
+        assert!(d_near < d_other, "{d_near} >= {d_other}");
+        assert_eq!(d_near, near.distance(&hash));
+    }
+}
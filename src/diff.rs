@@ -0,0 +1,534 @@
+/*! Corpus diff between two releases
+
+[CorpusDiff] matches the documents of two corpora (usually the same language in two OSCAR releases)
+by WARC record id or URL ([MatchBy]), and reports which documents were added, removed or changed.
+Changed documents come with per-field metadata changes ([FieldChange]) and content edit statistics ([ContentDiff]).
+
+Both corpora are sorted by key on disk (see [crate::sort]) before being compared,
+so memory usage doesn't depend on the corpus size.
+Any serializable [CorpusDocument] can be compared, so both OSCAR Schema v2 ([crate::oscar_doc]) and v3 ([crate::v3]) corpora are supported.
+
+```no_run
+use std::fs::File;
+use std::path::Path;
+
+use oscar_io::diff::{CorpusDiff, MatchBy};
+use oscar_io::v3::Reader;
+
+let old = Reader::from_path(Path::new("2023/fr_meta.jsonl")).unwrap();
+let new = Reader::from_path(Path::new("2024/fr_meta.jsonl")).unwrap();
+let summary = CorpusDiff::new(MatchBy::Url)
+    .write_jsonl(old, new, File::create("fr_diff.jsonl").unwrap())
+    .unwrap();
+println!("{summary:?}");
+```
+!*/
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use oxilangtag::LanguageTag;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::common::tmp::TmpDir;
+use crate::common::CorpusDocument;
+use crate::error::Error;
+use crate::sort::ExternalSort;
+use crate::v3::{Comp, NewWriter, WriterTrait};
+
+/// How documents of both corpora are matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchBy {
+    /// WARC record id.
+    RecordId,
+    /// Document URL.
+    Url,
+}
+
+impl MatchBy {
+    /// Get the key of a document, if it has one.
+    pub fn key<D: CorpusDocument>(&self, doc: &D) -> Option<String> {
+        match self {
+            MatchBy::RecordId => doc.record_id().map(Cow::into_owned),
+            MatchBy::Url => doc.url().map(Cow::into_owned),
+        }
+    }
+}
+
+/// Kind of change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// Change of a metadata field (`metadata.<field>` or `warc_headers.<header>`).
+/// A field missing on one side is `null`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// Content edit statistics.
+///
+/// Lines are compared as multisets: moved lines are not counted as edits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentDiff {
+    pub old_bytes: usize,
+    pub new_bytes: usize,
+    pub old_lines: usize,
+    pub new_lines: usize,
+    /// Lines of the old content that are not in the new one.
+    pub removed_lines: usize,
+    /// Lines of the new content that are not in the old one.
+    pub added_lines: usize,
+}
+
+impl ContentDiff {
+    pub fn new(old: &str, new: &str) -> Self {
+        let mut counts: HashMap<&str, isize> = HashMap::new();
+        for line in old.lines() {
+            *counts.entry(line).or_default() += 1;
+        }
+        for line in new.lines() {
+            *counts.entry(line).or_default() -= 1;
+        }
+        let (mut removed_lines, mut added_lines) = (0, 0);
+        for count in counts.values() {
+            match count.cmp(&0) {
+                Ordering::Greater => removed_lines += count.unsigned_abs(),
+                Ordering::Less => added_lines += count.unsigned_abs(),
+                Ordering::Equal => (),
+            }
+        }
+        Self {
+            old_bytes: old.len(),
+            new_bytes: new.len(),
+            old_lines: old.lines().count(),
+            new_lines: new.lines().count(),
+            removed_lines,
+            added_lines,
+        }
+    }
+}
+
+/// Added, removed or changed document. Unchanged documents are not reported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffEntry {
+    /// Record id or URL, depending on [MatchBy].
+    pub key: String,
+    pub status: Status,
+    /// Set for changed documents whose content changed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<ContentDiff>,
+    /// Set for changed documents whose metadata changed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldChange>,
+}
+
+/// Number of documents by status.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffSummary {
+    pub added: u64,
+    pub removed: u64,
+    pub changed: u64,
+    pub unchanged: u64,
+    /// Documents without key, which can't be matched.
+    pub skipped: u64,
+}
+
+/// Compares two corpora.
+#[derive(Debug, Clone)]
+pub struct CorpusDiff {
+    by: MatchBy,
+    run_size: usize,
+    tmp_dir: Option<PathBuf>,
+}
+
+impl CorpusDiff {
+    pub fn new(by: MatchBy) -> Self {
+        Self {
+            by,
+            run_size: 100_000,
+            tmp_dir: None,
+        }
+    }
+
+    /// Maximum number of documents kept in memory while sorting (see [ExternalSort]). Defaults to 100 000.
+    pub fn with_run_size(mut self, run_size: usize) -> Self {
+        self.run_size = run_size;
+        self
+    }
+
+    /// Directory in which sorted corpora are written. Defaults to [std::env::temp_dir].
+    pub fn with_tmp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.tmp_dir = Some(dir.into());
+        self
+    }
+
+    /// Compare `old` and `new`, sending entries to `sink` in key order.
+    ///
+    /// When several documents share a key, they are matched in corpus order.
+    pub fn diff<D, O, N, S>(&self, old: O, new: N, mut sink: S) -> Result<DiffSummary, Error>
+    where
+        D: CorpusDocument + Serialize + DeserializeOwned,
+        O: IntoIterator<Item = Result<D, Error>>,
+        N: IntoIterator<Item = Result<D, Error>>,
+        S: FnMut(DiffEntry) -> Result<(), Error>,
+    {
+        let tmp = TmpDir::create(self.tmp_dir.as_deref(), "diff")?;
+        let mut summary = DiffSummary::default();
+        let mut old = self
+            .sorted(old, &tmp.path().join("old"), &mut summary)?
+            .peekable();
+        let mut new = self
+            .sorted(new, &tmp.path().join("new"), &mut summary)?
+            .peekable();
+
+        loop {
+            let ordering = match (old.peek(), new.peek()) {
+                (None, None) => break,
+                (Some(Err(_)), _) | (Some(_), None) => Ordering::Less,
+                (_, Some(Err(_))) | (None, Some(_)) => Ordering::Greater,
+                (Some(Ok((a, _))), Some(Ok((b, _)))) => a.cmp(b),
+            };
+            // errors are returned by the branch reading them
+            match ordering {
+                Ordering::Less => {
+                    let (key, _) = old.next().expect("peeked")?;
+                    summary.removed += 1;
+                    sink(DiffEntry::new(key, Status::Removed))?;
+                }
+                Ordering::Greater => {
+                    let (key, _) = new.next().expect("peeked")?;
+                    summary.added += 1;
+                    sink(DiffEntry::new(key, Status::Added))?;
+                }
+                Ordering::Equal => {
+                    let (key, old_doc) = old.next().expect("peeked")?;
+                    let (_, new_doc) = new.next().expect("peeked")?;
+                    match DiffEntry::changed(key, &old_doc, &new_doc)? {
+                        Some(entry) => {
+                            summary.changed += 1;
+                            sink(entry)?;
+                        }
+                        None => summary.unchanged += 1,
+                    }
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Compare `old` and `new`, writing entries as JSON lines.
+    pub fn write_jsonl<D, O, N, W>(
+        &self,
+        old: O,
+        new: N,
+        mut writer: W,
+    ) -> Result<DiffSummary, Error>
+    where
+        D: CorpusDocument + Serialize + DeserializeOwned,
+        O: IntoIterator<Item = Result<D, Error>>,
+        N: IntoIterator<Item = Result<D, Error>>,
+        W: Write,
+    {
+        let summary = self.diff(old, new, |entry| {
+            serde_json::to_writer(&mut writer, &entry)?;
+            writer.write_all(b"\n")?;
+            Ok(())
+        })?;
+        writer.flush()?;
+        Ok(summary)
+    }
+
+    /// Sort documents having a key into `dir`, and stream them back with their key.
+    fn sorted<D, I>(
+        &self,
+        documents: I,
+        dir: &Path,
+        summary: &mut DiffSummary,
+    ) -> Result<impl Iterator<Item = Result<(String, D), Error>>, Error>
+    where
+        D: CorpusDocument + Serialize + DeserializeOwned,
+        I: IntoIterator<Item = Result<D, Error>>,
+    {
+        let by = self.by;
+        let documents = documents.into_iter().filter(|doc| match doc {
+            Ok(doc) if by.key(doc).is_none() => {
+                summary.skipped += 1;
+                false
+            }
+            _ => true,
+        });
+
+        std::fs::create_dir(dir)?;
+        let lang = LanguageTag::parse("und".to_string()).expect("valid language tag");
+        let mut writer = JsonlWriter::new(dir, lang, None, None)?;
+        ExternalSort::new(move |doc: &D| by.key(doc), self.run_size)
+            .with_tmp_dir(dir)
+            .sort(documents, &mut writer)?;
+        let path = writer.finish()?;
+
+        let sorted = BufReader::new(File::open(path)?).lines();
+        Ok(sorted.map(move |line| {
+            let doc: D = serde_json::from_str(&line?)?;
+            let key = by.key(&doc).expect("documents without key are skipped");
+            Ok((key, doc))
+        }))
+    }
+}
+
+impl DiffEntry {
+    fn new(key: String, status: Status) -> Self {
+        Self {
+            key,
+            status,
+            content: None,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Compare two matched documents. Returns [None] if they are identical.
+    fn changed<D>(key: String, old: &D, new: &D) -> Result<Option<Self>, Error>
+    where
+        D: CorpusDocument + Serialize,
+    {
+        let content = (old.content() != new.content())
+            .then(|| ContentDiff::new(old.content(), new.content()));
+
+        let (mut old_fields, new_fields) = (fields(old)?, fields(new)?);
+        let mut changes = Vec::new();
+        for (field, new) in new_fields {
+            let old = old_fields.remove(&field).unwrap_or(Value::Null);
+            if old != new {
+                changes.push(FieldChange { field, old, new });
+            }
+        }
+        changes.extend(old_fields.into_iter().map(|(field, old)| FieldChange {
+            field,
+            old,
+            new: Value::Null,
+        }));
+        changes.sort_by(|a, b| a.field.cmp(&b.field));
+
+        if content.is_none() && changes.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            content,
+            fields: changes,
+            ..Self::new(key, Status::Changed)
+        }))
+    }
+}
+
+/// Flatten the serialized document (without content) into `<object>.<field>` values.
+fn fields<D: Serialize>(doc: &D) -> Result<BTreeMap<String, Value>, Error> {
+    let mut fields = BTreeMap::new();
+    if let Value::Object(doc) = serde_json::to_value(doc)? {
+        for (name, value) in doc {
+            match value {
+                _ if name == "content" => (),
+                Value::Object(inner) => fields.extend(
+                    inner
+                        .into_iter()
+                        .map(|(field, value)| (format!("{name}.{field}"), value)),
+                ),
+                value => {
+                    fields.insert(name, value);
+                }
+            }
+        }
+    }
+    Ok(fields)
+}
+
+/// Writes sorted documents of any version as JSON lines, without rotation.
+struct JsonlWriter<D> {
+    handle: NewWriter,
+    document: PhantomData<D>,
+}
+
+impl<D> JsonlWriter<D> {
+    /// Flushes the writer and returns the path of the written file.
+    fn finish(mut self) -> Result<PathBuf, Error> {
+        self.handle.flush()?;
+        Ok(self.handle.current_filepath())
+    }
+}
+
+impl<D: Serialize> WriterTrait for JsonlWriter<D> {
+    type Item = D;
+
+    fn new(
+        dst: &Path,
+        lang: LanguageTag<String>,
+        max_file_size: Option<u64>,
+        comp: Option<Comp>,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            handle: NewWriter::new(
+                dst,
+                lang.to_string(),
+                comp,
+                max_file_size.map(|x| x as usize),
+            )?,
+            document: PhantomData,
+        })
+    }
+
+    fn write(&mut self, vals: Vec<D>) -> Result<(), Error> {
+        for val in &vals {
+            self.write_single(val)?;
+        }
+        Ok(())
+    }
+
+    fn write_single(&mut self, val: &D) -> Result<(), Error> {
+        serde_json::to_writer(&mut self.handle, val)?;
+        self.handle.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{ContentDiff, CorpusDiff, DiffEntry, DiffSummary, MatchBy, Status};
+    use crate::test_utils::{self, with_url, with_warnings};
+    use crate::v3::Document;
+
+    fn doc(url: &str, content: &str, warnings: &[&str]) -> Document {
+        let mut doc = with_url(with_warnings(test_utils::doc(content), warnings), url);
+        // builders set random record ids and dates
+        doc.warc_headers_mut().remove(&warc::WarcHeader::RecordID);
+        doc.warc_headers_mut().remove(&warc::WarcHeader::Date);
+        doc
+    }
+
+    #[test]
+    fn test_content_diff() {
+        let diff = ContentDiff::new("a\nb\nc\nc", "c\na\nd\n");
+        assert_eq!(
+            diff,
+            ContentDiff {
+                old_bytes: 7,
+                new_bytes: 6,
+                old_lines: 4,
+                new_lines: 3,
+                removed_lines: 2,
+                added_lines: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = vec![
+            doc("https://a.com/", "same", &[]),
+            doc("https://b.com/", "removed", &[]),
+            doc("https://d.com/", "old line\nkept line", &[]),
+            doc("https://e.com/", "flagged", &[]),
+        ];
+        let new = vec![
+            doc("https://e.com/", "flagged", &["tiny"]),
+            doc("https://d.com/", "new line\nkept line", &[]),
+            doc("https://c.com/", "added", &[]),
+            doc("https://a.com/", "same", &[]),
+        ];
+        let mut no_url = doc("https://f.com/", "no url", &[]);
+        no_url
+            .warc_headers_mut()
+            .remove(&warc::WarcHeader::TargetURI);
+
+        let tmp = tempfile::tempdir().unwrap();
+        let mut out = Vec::new();
+        let summary = CorpusDiff::new(MatchBy::Url)
+            .with_run_size(2)
+            .with_tmp_dir(tmp.path())
+            .write_jsonl(
+                old.into_iter().map(Ok),
+                new.into_iter().chain([no_url]).map(Ok),
+                &mut out,
+            )
+            .unwrap();
+        assert_eq!(
+            summary,
+            DiffSummary {
+                added: 1,
+                removed: 1,
+                changed: 2,
+                unchanged: 1,
+                skipped: 1,
+            }
+        );
+
+        let entries: Vec<DiffEntry> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let statuses: Vec<(&str, Status)> =
+            entries.iter().map(|e| (e.key.as_str(), e.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("https://b.com/", Status::Removed),
+                ("https://c.com/", Status::Added),
+                ("https://d.com/", Status::Changed),
+                ("https://e.com/", Status::Changed),
+            ]
+        );
+
+        let content = entries[2].content.unwrap();
+        assert_eq!((content.removed_lines, content.added_lines), (1, 1));
+        assert!(entries[2].fields.is_empty());
+
+        assert!(entries[3].content.is_none());
+        assert_eq!(entries[3].fields.len(), 1);
+        assert_eq!(entries[3].fields[0].field, "metadata.quality_warnings");
+        assert_eq!(entries[3].fields[0].new, serde_json::json!(["tiny"]));
+
+        // temporary files are removed
+        assert_eq!(std::fs::read_dir(tmp.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_diff_oscar_doc() {
+        use crate::common::WarcHeaders;
+        use crate::oscar_doc::{Document, Metadata};
+
+        let doc = |url: &str, content: &str| {
+            let headers: WarcHeaders = [(warc::WarcHeader::TargetURI, url.as_bytes().to_vec())]
+                .into_iter()
+                .collect();
+            let metadata = Metadata::new(&test_utils::id("en", 1.0), &None, &[]);
+            Document::new(content.to_string(), headers, metadata)
+        };
+        let old = vec![doc("https://a.com/", "same"), doc("https://b.com/", "old")];
+        let new = vec![doc("https://b.com/", "new"), doc("https://a.com/", "same")];
+
+        let mut entries = Vec::new();
+        let summary = CorpusDiff::new(MatchBy::Url)
+            .diff(old.into_iter().map(Ok), new.into_iter().map(Ok), |entry| {
+                entries.push(entry);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!((summary.changed, summary.unchanged), (1, 1));
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key, "https://b.com/");
+        assert_eq!(entries[0].status, Status::Changed);
+    }
+}
@@ -1,4 +1,8 @@
+use std::fmt::Display;
 use std::string::FromUtf8Error;
+
+use warc::WarcHeader;
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub enum Error {
@@ -8,6 +12,9 @@ pub enum Error {
     Custom(String),
     Avro(avro_rs::DeError),
     SerdeJson(serde_json::Error),
+    Warc(warc::Error),
+    MalformedHeader(warc::WarcHeader, String),
+    Validation(Vec<ValidationError>),
 }
 
 impl From<avro_rs::DeError> for Error {
@@ -39,3 +46,54 @@ impl From<serde_json::Error> for Error {
         Error::SerdeJson(e)
     }
 }
+
+impl From<warc::Error> for Error {
+    fn from(e: warc::Error) -> Error {
+        Error::Warc(e)
+    }
+}
+
+/// A problem found when validating a document, see [crate::v3::Document::validate].
+///
+/// Validation reports every problem at once, in an [Error::Validation].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// A mandatory header is missing.
+    MissingHeader(WarcHeader),
+    /// A header value is not valid UTF-8.
+    InvalidUtf8(WarcHeader),
+    /// A header value can't be parsed (record id, date...).
+    MalformedHeader(WarcHeader, String),
+    /// The number of sentence identifications doesn't match the number of lines.
+    SentenceCountMismatch {
+        lines: usize,
+        identifications: usize,
+    },
+    /// A probability is outside of `[0, 1]` (values up to 1.001 are accepted, as fastText can slightly exceed 1).
+    /// `line` is [None] for the document identification.
+    ProbOutOfRange { line: Option<usize>, prob: f32 },
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingHeader(h) => write!(f, "missing header {h}"),
+            Self::InvalidUtf8(h) => write!(f, "header {h} is not valid UTF-8"),
+            Self::MalformedHeader(h, reason) => write!(f, "malformed header {h}: {reason}"),
+            Self::SentenceCountMismatch {
+                lines,
+                identifications,
+            } => write!(
+                f,
+                "{identifications} sentence identifications for {lines} lines"
+            ),
+            Self::ProbOutOfRange { line: None, prob } => {
+                write!(f, "document probability {prob} is not in [0, 1]")
+            }
+            Self::ProbOutOfRange {
+                line: Some(line),
+                prob,
+            } => write!(f, "probability {prob} of line {line} is not in [0, 1]"),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Boolean combinators over [Filter]s.
+use crate::common::CorpusDocument;
+
+use super::{Filter, FilterOps};
+
+/// Keeps documents matching both filters. See [FilterOps::and].
+#[derive(Debug, Clone)]
+pub struct And<A, B>(pub A, pub B);
+
+impl<D: CorpusDocument, A: Filter<D>, B: Filter<D>> Filter<D> for And<A, B> {
+    fn keep(&self, doc: &D) -> bool {
+        self.0.keep(doc) && self.1.keep(doc)
+    }
+}
+
+impl<A, B> FilterOps for And<A, B> {}
+
+/// Keeps documents matching any of the filters. See [FilterOps::or].
+#[derive(Debug, Clone)]
+pub struct Or<A, B>(pub A, pub B);
+
+impl<D: CorpusDocument, A: Filter<D>, B: Filter<D>> Filter<D> for Or<A, B> {
+    fn keep(&self, doc: &D) -> bool {
+        self.0.keep(doc) || self.1.keep(doc)
+    }
+}
+
+impl<A, B> FilterOps for Or<A, B> {}
+
+/// Keeps documents not matching the filter. See [FilterOps::not].
+#[derive(Debug, Clone)]
+pub struct Not<A>(pub A);
+
+impl<D: CorpusDocument, A: Filter<D>> Filter<D> for Not<A> {
+    fn keep(&self, doc: &D) -> bool {
+        !self.0.keep(doc)
+    }
+}
+
+impl<A> FilterOps for Not<A> {}
+
+/// Keeps documents matching every filter (all documents if there are none).
+pub struct All<D: CorpusDocument>(pub Vec<Box<dyn Filter<D>>>);
+
+impl<D: CorpusDocument> Default for All<D> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<D: CorpusDocument> Filter<D> for All<D> {
+    fn keep(&self, doc: &D) -> bool {
+        self.0.iter().all(|f| f.keep(doc))
+    }
+}
+
+impl<D: CorpusDocument> FilterOps for All<D> {}
+
+/// Keeps documents matching at least one filter (no documents if there are none).
+pub struct Any<D: CorpusDocument>(pub Vec<Box<dyn Filter<D>>>);
+
+impl<D: CorpusDocument> Default for Any<D> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<D: CorpusDocument> Filter<D> for Any<D> {
+    fn keep(&self, doc: &D) -> bool {
+        self.0.iter().any(|f| f.keep(doc))
+    }
+}
+
+impl<D: CorpusDocument> FilterOps for Any<D> {}
+
+/// Closure filter. See [super::from_fn].
+#[derive(Debug, Clone)]
+pub struct FnFilter<F>(pub(crate) F);
+
+impl<D: CorpusDocument, F: Fn(&D) -> bool> Filter<D> for FnFilter<F> {
+    fn keep(&self, doc: &D) -> bool {
+        (self.0)(doc)
+    }
+}
+
+impl<F> FilterOps for FnFilter<F> {}
@@ -0,0 +1,256 @@
+//! Expression tree, type checking and evaluation.
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::ops::Range;
+
+use super::fields::{Field, Fields, Type, Value};
+use super::ExprError;
+
+/// Comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    fn matches(&self, ordering: Ordering) -> bool {
+        match self {
+            CmpOp::Eq => ordering.is_eq(),
+            CmpOp::Ne => ordering.is_ne(),
+            CmpOp::Lt => ordering.is_lt(),
+            CmpOp::Le => ordering.is_le(),
+            CmpOp::Gt => ordering.is_gt(),
+            CmpOp::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+impl Display for CmpOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self {
+            CmpOp::Eq => "==",
+            CmpOp::Ne => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+        };
+        f.write_str(op)
+    }
+}
+
+/// Method call on a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Method {
+    Contains,
+    StartsWith,
+    EndsWith,
+    Len,
+}
+
+impl Method {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "contains" => Some(Method::Contains),
+            "starts_with" => Some(Method::StartsWith),
+            "ends_with" => Some(Method::EndsWith),
+            "len" => Some(Method::Len),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Method::Contains => "contains",
+            Method::StartsWith => "starts_with",
+            Method::EndsWith => "ends_with",
+            Method::Len => "len",
+        }
+    }
+
+    /// Receiver types, argument type and return type.
+    fn signature(&self) -> (&'static [Type], Option<Type>, Type) {
+        match self {
+            Method::Contains => (&[Type::String, Type::List], Some(Type::String), Type::Bool),
+            Method::StartsWith | Method::EndsWith => {
+                (&[Type::String], Some(Type::String), Type::Bool)
+            }
+            Method::Len => (&[Type::String, Type::List], None, Type::Number),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ExprKind {
+    Literal(Value<'static>),
+    Field(Field),
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Compare(CmpOp, Box<Node>, Box<Node>),
+    /// `value in list`
+    In(Box<Node>, Box<Node>),
+    Call(Method, Box<Node>, Option<Box<Node>>),
+}
+
+/// Expression, with its position in the source.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Node {
+    pub kind: ExprKind,
+    pub span: Range<usize>,
+}
+
+fn literal_type(value: &Value) -> Type {
+    match value {
+        Value::Bool(_) => Type::Bool,
+        Value::Number(_) => Type::Number,
+        Value::String(_) | Value::Missing => Type::String,
+        Value::List(_) => Type::List,
+    }
+}
+
+impl Node {
+    pub(crate) fn new(kind: ExprKind, span: Range<usize>) -> Self {
+        Self { kind, span }
+    }
+
+    fn expect(&self, expected: Type) -> Result<(), ExprError> {
+        let ty = self.check()?;
+        if ty == expected {
+            Ok(())
+        } else {
+            Err(ExprError::new(
+                format!("expected {expected}, found {ty}"),
+                self.span.clone(),
+            ))
+        }
+    }
+
+    /// Type check the expression, returning its type.
+    pub(crate) fn check(&self) -> Result<Type, ExprError> {
+        match &self.kind {
+            ExprKind::Literal(value) => Ok(literal_type(value)),
+            ExprKind::Field(field) => Ok(field.ty()),
+            ExprKind::Not(e) => e.expect(Type::Bool).map(|_| Type::Bool),
+            ExprKind::And(a, b) | ExprKind::Or(a, b) => {
+                a.expect(Type::Bool)?;
+                b.expect(Type::Bool)?;
+                Ok(Type::Bool)
+            }
+            ExprKind::Compare(op, a, b) => {
+                let ty = a.check()?;
+                let valid = match op {
+                    CmpOp::Eq | CmpOp::Ne => ty != Type::List,
+                    _ => ty == Type::Number || ty == Type::String,
+                };
+                if !valid {
+                    return Err(ExprError::new(
+                        format!("can't use `{op}` on {ty}"),
+                        a.span.clone(),
+                    ));
+                }
+                b.expect(ty)?;
+                Ok(Type::Bool)
+            }
+            ExprKind::In(a, b) => {
+                a.expect(Type::String)?;
+                b.expect(Type::List)?;
+                Ok(Type::Bool)
+            }
+            ExprKind::Call(method, receiver, arg) => {
+                let (receivers, arg_ty, ret) = method.signature();
+                let ty = receiver.check()?;
+                if !receivers.contains(&ty) {
+                    return Err(ExprError::new(
+                        format!("no method `{}` on {ty}", method.name()),
+                        self.span.clone(),
+                    ));
+                }
+                match (arg_ty, arg) {
+                    (Some(arg_ty), Some(arg)) => arg.expect(arg_ty)?,
+                    (None, None) => (),
+                    (Some(_), None) => {
+                        return Err(ExprError::new(
+                            format!("`{}` takes one argument", method.name()),
+                            self.span.clone(),
+                        ))
+                    }
+                    (None, Some(arg)) => {
+                        return Err(ExprError::new(
+                            format!("`{}` takes no argument", method.name()),
+                            arg.span.clone(),
+                        ))
+                    }
+                }
+                Ok(ret)
+            }
+        }
+    }
+
+    /// Evaluate the expression. Assumes it has been type checked.
+    ///
+    /// Any operation on a [Value::Missing] is `false` (or missing), including `!=`.
+    pub(crate) fn eval<'a, D: Fields>(&'a self, doc: &'a D) -> Value<'a> {
+        match &self.kind {
+            ExprKind::Literal(value) => value.clone(),
+            ExprKind::Field(field) => doc.field(*field),
+            ExprKind::Not(e) => match e.eval(doc) {
+                Value::Bool(b) => Value::Bool(!b),
+                _ => Value::Bool(false),
+            },
+            ExprKind::And(a, b) => Value::Bool(a.eval_bool(doc) && b.eval_bool(doc)),
+            ExprKind::Or(a, b) => Value::Bool(a.eval_bool(doc) || b.eval_bool(doc)),
+            ExprKind::Compare(op, a, b) => {
+                let ordering = match (a.eval(doc), b.eval(doc)) {
+                    (Value::Number(a), Value::Number(b)) => a.partial_cmp(&b),
+                    (Value::String(a), Value::String(b)) => Some(a.cmp(&b)),
+                    (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(&b)),
+                    _ => None,
+                };
+                Value::Bool(ordering.is_some_and(|o| op.matches(o)))
+            }
+            ExprKind::In(a, b) => match (a.eval(doc), b.eval(doc)) {
+                (Value::String(s), Value::List(l)) => Value::Bool(l.contains(&s)),
+                _ => Value::Bool(false),
+            },
+            ExprKind::Call(method, receiver, arg) => {
+                let arg = arg.as_ref().map(|arg| arg.eval(doc));
+                match (method, receiver.eval(doc), arg) {
+                    (Method::Len, Value::String(s), None) => {
+                        Value::Number(s.chars().count() as f64)
+                    }
+                    (Method::Len, Value::List(l), None) => Value::Number(l.len() as f64),
+                    (Method::Contains, Value::List(l), Some(Value::String(s))) => {
+                        Value::Bool(l.contains(&s))
+                    }
+                    (Method::Contains, Value::String(s), Some(Value::String(sub))) => {
+                        Value::Bool(s.contains(sub.as_ref()))
+                    }
+                    (Method::StartsWith, Value::String(s), Some(Value::String(sub))) => {
+                        Value::Bool(s.starts_with(sub.as_ref()))
+                    }
+                    (Method::EndsWith, Value::String(s), Some(Value::String(sub))) => {
+                        Value::Bool(s.ends_with(sub.as_ref()))
+                    }
+                    (Method::Len, _, _) => Value::Missing,
+                    _ => Value::Bool(false),
+                }
+            }
+        }
+    }
+
+    pub(crate) fn eval_bool<D: Fields>(&self, doc: &D) -> bool {
+        self.eval(doc) == Value::Bool(true)
+    }
+}
+
+/// Literal list of strings.
+pub(crate) fn list(items: Vec<String>) -> Value<'static> {
+    Value::List(items.into_iter().map(Cow::Owned).collect())
+}
@@ -0,0 +1,121 @@
+//! Document fields available in expressions, their types and values.
+use std::borrow::Cow;
+use std::fmt::Display;
+
+use crate::common::CorpusDocument;
+
+/// Type of an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Bool,
+    Number,
+    String,
+    /// List of strings.
+    List,
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Type::Bool => "bool",
+            Type::Number => "number",
+            Type::String => "string",
+            Type::List => "list",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Value of an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<'a> {
+    Bool(bool),
+    Number(f64),
+    String(Cow<'a, str>),
+    List(Vec<Cow<'a, str>>),
+    /// Optional field that is not set on a document.
+    Missing,
+}
+
+macro_rules! fields {
+    ($($variant:ident => $name:literal: $ty:ident, $doc:literal;)*) => {
+        /// Document field.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Field {
+            $(#[doc = $doc] $variant,)*
+        }
+
+        impl Field {
+            /// Every field, in documentation order.
+            pub const ALL: &'static [Field] = &[$(Field::$variant),*];
+
+            /// Name of the field in expressions.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Field::$variant => $name,)*
+                }
+            }
+
+            pub fn ty(&self) -> Type {
+                match self {
+                    $(Field::$variant => Type::$ty,)*
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($name => Some(Field::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+fields! {
+    Lang => "lang": String, "Document language (ex. `fr`).";
+    Prob => "prob": Number, "Document language identification probability.";
+    Content => "content": String, "Document content.";
+    Length => "length": Number, "Content length, in characters.";
+    Lines => "lines": Number, "Number of lines.";
+    Url => "url": String, "Document URL (optional).";
+    Domain => "domain": String, "Host of the document URL (optional).";
+    RecordId => "record_id": String, "WARC record id (optional).";
+    Warnings => "warnings": List, "Quality warnings (annotations in OSCAR 22.01).";
+    Categories => "categories": List, "Categories (always empty in OSCAR 22.01).";
+    HarmfulPp => "harmful_pp": Number, "Harmful perplexity (optional, never set in OSCAR 22.01).";
+}
+
+/// Documents that can be evaluated by a [super::Expression].
+///
+/// Implemented for every [CorpusDocument].
+pub trait Fields {
+    /// Get the value of a field.
+    fn field(&self, field: Field) -> Value<'_>;
+}
+
+fn optional(value: Option<Cow<'_, str>>) -> Value<'_> {
+    value.map_or(Value::Missing, Value::String)
+}
+
+impl<D: CorpusDocument> Fields for D {
+    fn field(&self, field: Field) -> Value<'_> {
+        match field {
+            Field::Lang => Value::String(self.identification().label().as_str().into()),
+            Field::Prob => Value::Number(*self.identification().prob() as f64),
+            Field::Content => Value::String(self.content().into()),
+            Field::Length => Value::Number(self.content().chars().count() as f64),
+            Field::Lines => Value::Number(self.content().lines().count() as f64),
+            Field::Url => optional(self.url()),
+            Field::Domain => optional(self.host().map(Cow::Owned)),
+            Field::RecordId => optional(self.record_id()),
+            Field::Warnings => Value::List(self.annotations().into_iter().map(Cow::from).collect()),
+            Field::Categories => {
+                Value::List(self.categories().into_iter().map(Cow::from).collect())
+            }
+            Field::HarmfulPp => self
+                .harmful_pp()
+                .map_or(Value::Missing, |pp| Value::Number(pp as f64)),
+        }
+    }
+}
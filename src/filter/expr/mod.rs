@@ -0,0 +1,234 @@
+/*! Filter expressions
+
+Expressions select documents from a textual description, for use on the command line or in configuration files:
+
+```text
+lang in ["fr", "br"] && prob > 0.8 && !warnings.contains("noisy") && harmful_pp < 500
+```
+
+- fields: see [Field] (`lang`, `prob`, `content`, `length`, `lines`, `url`, `domain`, `record_id`, `warnings`, `categories`, `harmful_pp`),
+- literals: strings (`"fr"`), numbers (`0.8`, `-1`), booleans (`true`, `false`) and lists of strings (`["fr", "br"]`),
+- operators: `||`, `&&`, `!`, `==`, `!=`, `<`, `<=`, `>`, `>=` and `in`, in increasing order of precedence, `!` applying to comparisons,
+- methods: `contains` (strings and lists), `starts_with`, `ends_with` (strings) and `len` (strings and lists).
+
+Expressions are type checked when parsed.
+Comparisons on optional fields that are not set (such as `harmful_pp` or `url`) are always `false`, including `!=`.
+
+```
+use oscar_io::filter::expr::Expression;
+
+let expr: Expression = r#"lang == "fr" && prob > 0.8"#.parse().unwrap();
+
+let err = "lang > 0.8".parse::<Expression>().unwrap_err();
+assert_eq!(err.to_string(), "expected string, found number at 7..10");
+assert_eq!(err.render("lang > 0.8"), "lang > 0.8\n       ^^^ expected string, found number");
+```
+!*/
+mod ast;
+mod fields;
+mod parser;
+
+use std::fmt::Display;
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::common::CorpusDocument;
+use crate::error::Error;
+
+use super::{Filter, FilterOps};
+
+pub use fields::{Field, Fields, Type, Value};
+
+/// Parse or type error, located in the source expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExprError {
+    message: String,
+    span: Range<usize>,
+}
+
+impl ExprError {
+    pub(crate) fn new(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// Get the error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Get the byte range of the error in the source expression.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// Show the error under the source expression.
+    pub fn render(&self, src: &str) -> String {
+        let offset = src[..self.span.start.min(src.len())].chars().count();
+        let width = src
+            .get(self.span.clone())
+            .map_or(1, |s| s.chars().count().max(1));
+        format!(
+            "{src}\n{}{} {}",
+            " ".repeat(offset),
+            "^".repeat(width),
+            self.message
+        )
+    }
+}
+
+impl Display for ExprError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {:?}", self.message, self.span)
+    }
+}
+
+impl From<ExprError> for Error {
+    fn from(e: ExprError) -> Self {
+        Error::Custom(e.to_string())
+    }
+}
+
+/// A parsed and type checked filter expression.
+///
+/// Can be used as a [Filter] on any [CorpusDocument], or on any document implementing [Fields]
+/// with [Expression::matches] and [Expression::filter].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    root: ast::Node,
+}
+
+impl Expression {
+    /// Parse and type check an expression.
+    pub fn parse(src: &str) -> Result<Self, ExprError> {
+        let root = parser::parse(src)?;
+        let ty = root.check()?;
+        if ty != Type::Bool {
+            return Err(ExprError::new(
+                format!("expression must be a bool, found {ty}"),
+                root.span.clone(),
+            ));
+        }
+        Ok(Self { root })
+    }
+
+    /// Checks if a document matches the expression.
+    pub fn matches<D: Fields>(&self, doc: &D) -> bool {
+        self.root.eval_bool(doc)
+    }
+
+    /// Keep documents matching the expression. Errors are passed through.
+    pub fn filter<'a, D, I>(&'a self, documents: I) -> impl Iterator<Item = Result<D, Error>> + 'a
+    where
+        D: Fields + 'a,
+        I: Iterator<Item = Result<D, Error>> + 'a,
+    {
+        documents.filter(move |doc| doc.as_ref().map_or(true, |doc| self.matches(doc)))
+    }
+}
+
+impl FromStr for Expression {
+    type Err = ExprError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl<D: CorpusDocument> Filter<D> for Expression {
+    fn keep(&self, doc: &D) -> bool {
+        self.matches(doc)
+    }
+}
+
+impl FilterOps for Expression {}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use super::Expression;
+    use crate::filter::tests::doc;
+    use crate::filter::FilterExt;
+    use crate::oscar_doc;
+
+    fn matches(src: &str, doc: &crate::v3::Document) -> bool {
+        src.parse::<Expression>().unwrap().matches(doc)
+    }
+
+    #[test]
+    fn test_v3() {
+        let mut d = doc("foo bar\nbaz", "fr", 0.9, &["tiny"]);
+        assert!(matches(r#"lang == "fr" && prob > 0.8"#, &d));
+        assert!(matches(r#"lang in ["br", "fr"]"#, &d));
+        assert!(matches(
+            r#"!warnings.contains("noisy") && warnings.contains("tiny")"#,
+            &d
+        ));
+        assert!(matches(
+            r#"lines == 2 && length >= 11 && content.starts_with("foo")"#,
+            &d
+        ));
+        assert!(matches(
+            r#"domain.ends_with("example.com") && url != "foo""#,
+            &d
+        ));
+        assert!(matches(r#"categories.len() == 0 || false"#, &d));
+        assert!(!matches(r#"!(lang == "fr")"#, &d));
+
+        // missing values
+        assert!(!matches("harmful_pp < 500", &d));
+        assert!(!matches("harmful_pp != 500", &d));
+        assert!(matches("!(harmful_pp >= 500)", &d));
+        d.metadata_mut().set_harmful_pp(Some(100.0));
+        assert!(matches("harmful_pp < 500", &d));
+    }
+
+    #[test]
+    fn test_type_errors() {
+        let cases = [
+            ("prob", "expression must be a bool, found number", 0..4),
+            ("lang > 0.8", "expected string, found number", 7..10),
+            ("warnings == \"tiny\"", "can't use `==` on list", 0..8),
+            (
+                "prob.contains(\"a\")",
+                "no method `contains` on number",
+                0..18,
+            ),
+            ("lang.contains()", "`contains` takes one argument", 0..15),
+            ("lang.len(\"a\")", "`len` takes no argument", 9..12),
+            ("prob > 1 && lang", "expected bool, found string", 12..16),
+            ("1 in warnings", "expected string, found number", 0..1),
+        ];
+        for (src, message, span) in cases {
+            let err = src.parse::<Expression>().unwrap_err();
+            assert_eq!(err.message(), message, "{src}");
+            assert_eq!(err.span(), span, "{src}");
+        }
+    }
+
+    #[test]
+    fn test_readers() {
+        let expr: Expression = r#"lang == "en" && warnings.contains("header")"#.parse().unwrap();
+        let f = File::open("tests/res/data.jsonl").unwrap();
+        let docs: Vec<oscar_doc::Document> = expr
+            .filter(oscar_doc::Reader::new(BufReader::new(f)))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(!docs.is_empty());
+        assert!(docs.iter().all(|d| expr.matches(d)));
+
+        let v3_docs = vec![
+            Ok(doc("foo", "fr", 0.9, &[])),
+            Ok(doc("bar", "en", 0.9, &[])),
+        ];
+        let kept = v3_docs
+            .into_iter()
+            .filter_documents(r#"lang == "en""#.parse::<Expression>().unwrap())
+            .count();
+        assert_eq!(kept, 1);
+    }
+}
@@ -0,0 +1,368 @@
+//! Lexer and recursive descent parser.
+//!
+//! ```text
+//! or      := and ("||" and)*
+//! and     := unary ("&&" unary)*
+//! unary   := "!" unary | compare
+//! compare := postfix (("==" | "!=" | "<" | "<=" | ">" | ">=" | "in") postfix)?
+//! postfix := primary ("." ident "(" or? ")")*
+//! primary := string | number | "true" | "false" | "[" (string ("," string)*)? "]" | ident | "(" or ")"
+//! ```
+use std::borrow::Cow;
+use std::ops::Range;
+
+use super::ast::{list, CmpOp, ExprKind, Method, Node};
+use super::fields::{Field, Value};
+use super::ExprError;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Op(&'static str),
+    Eof,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Ident(i) => format!("`{i}`"),
+            Token::Str(s) => format!("{s:?}"),
+            Token::Num(n) => format!("`{n}`"),
+            Token::Op(op) => format!("`{op}`"),
+            Token::Eof => "end of expression".to_string(),
+        }
+    }
+}
+
+/// Operators, longest first.
+const OPS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "(", ")", "[", "]", ",", ".",
+];
+
+fn lex(src: &str) -> Result<Vec<(Token, Range<usize>)>, ExprError> {
+    let mut tokens = Vec::new();
+    let mut chars = src.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '"')) => break,
+                    Some((i, '\\')) => match chars.next() {
+                        Some((_, '"')) => s.push('"'),
+                        Some((_, '\\')) => s.push('\\'),
+                        Some((_, 'n')) => s.push('\n'),
+                        Some((_, 't')) => s.push('\t'),
+                        _ => return Err(ExprError::new("invalid escape sequence", i..i + 2)),
+                    },
+                    Some((_, c)) => s.push(c),
+                    None => return Err(ExprError::new("unterminated string", start..src.len())),
+                }
+            }
+            let end = chars.peek().map_or(src.len(), |(i, _)| *i);
+            tokens.push((Token::Str(s), start..end));
+        } else if c.is_ascii_digit()
+            || (c == '-' && src[start + 1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            chars.next();
+            let mut end = src.len();
+            while let Some(&(i, c)) = chars.peek() {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+                    chars.next();
+                } else {
+                    end = i;
+                    break;
+                }
+            }
+            let num = src[start..end]
+                .parse()
+                .map_err(|_| ExprError::new("invalid number", start..end))?;
+            tokens.push((Token::Num(num), start..end));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = src.len();
+            while let Some(&(i, c)) = chars.peek() {
+                if c.is_alphanumeric() || c == '_' {
+                    chars.next();
+                } else {
+                    end = i;
+                    break;
+                }
+            }
+            tokens.push((Token::Ident(src[start..end].to_string()), start..end));
+        } else if let Some(op) = OPS.iter().find(|op| src[start..].starts_with(*op)) {
+            for _ in 0..op.len() {
+                chars.next();
+            }
+            tokens.push((Token::Op(op), start..start + op.len()));
+        } else {
+            let end = start + c.len_utf8();
+            return Err(ExprError::new(
+                format!("unexpected character `{c}`"),
+                start..end,
+            ));
+        }
+    }
+    tokens.push((Token::Eof, src.len()..src.len()));
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, Range<usize>)>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    fn span(&self) -> Range<usize> {
+        self.tokens[self.pos].1.clone()
+    }
+
+    fn next(&mut self) -> (Token, Range<usize>) {
+        let token = self.tokens[self.pos].clone();
+        if self.pos < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, op: &'static str) -> bool {
+        if self.peek() == &Token::Op(op) {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> ExprError {
+        ExprError::new(
+            format!("expected {expected}, found {}", self.peek().describe()),
+            self.span(),
+        )
+    }
+
+    fn expect(&mut self, op: &'static str) -> Result<Range<usize>, ExprError> {
+        let span = self.span();
+        if self.eat(op) {
+            Ok(span)
+        } else {
+            Err(self.unexpected(&format!("`{op}`")))
+        }
+    }
+
+    fn or(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.and()?;
+        while self.eat("||") {
+            let rhs = self.and()?;
+            let span = node.span.start..rhs.span.end;
+            node = Node::new(ExprKind::Or(Box::new(node), Box::new(rhs)), span);
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.unary()?;
+        while self.eat("&&") {
+            let rhs = self.unary()?;
+            let span = node.span.start..rhs.span.end;
+            node = Node::new(ExprKind::And(Box::new(node), Box::new(rhs)), span);
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> Result<Node, ExprError> {
+        let start = self.span().start;
+        if self.eat("!") {
+            let e = self.unary()?;
+            let span = start..e.span.end;
+            return Ok(Node::new(ExprKind::Not(Box::new(e)), span));
+        }
+        self.compare()
+    }
+
+    fn compare(&mut self) -> Result<Node, ExprError> {
+        let lhs = self.postfix()?;
+        let op = match self.peek() {
+            Token::Op("==") => Some(CmpOp::Eq),
+            Token::Op("!=") => Some(CmpOp::Ne),
+            Token::Op("<") => Some(CmpOp::Lt),
+            Token::Op("<=") => Some(CmpOp::Le),
+            Token::Op(">") => Some(CmpOp::Gt),
+            Token::Op(">=") => Some(CmpOp::Ge),
+            Token::Ident(i) if i == "in" => None,
+            _ => return Ok(lhs),
+        };
+        self.next();
+        let rhs = self.postfix()?;
+        let span = lhs.span.start..rhs.span.end;
+        let kind = match op {
+            Some(op) => ExprKind::Compare(op, Box::new(lhs), Box::new(rhs)),
+            None => ExprKind::In(Box::new(lhs), Box::new(rhs)),
+        };
+        Ok(Node::new(kind, span))
+    }
+
+    fn postfix(&mut self) -> Result<Node, ExprError> {
+        let mut node = self.primary()?;
+        while self.eat(".") {
+            let (token, name_span) = self.next();
+            let method = match &token {
+                Token::Ident(name) => Method::from_name(name).ok_or_else(|| {
+                    ExprError::new(
+                        format!("unknown method `{name}` (expected contains, starts_with, ends_with or len)"),
+                        name_span.clone(),
+                    )
+                })?,
+                token => {
+                    return Err(ExprError::new(
+                        format!("expected method name, found {}", token.describe()),
+                        name_span,
+                    ))
+                }
+            };
+            self.expect("(")?;
+            let arg = if self.peek() == &Token::Op(")") {
+                None
+            } else {
+                Some(Box::new(self.or()?))
+            };
+            let end = self.expect(")")?.end;
+            let span = node.span.start..end;
+            node = Node::new(ExprKind::Call(method, Box::new(node), arg), span);
+        }
+        Ok(node)
+    }
+
+    fn primary(&mut self) -> Result<Node, ExprError> {
+        let (token, span) = self.next();
+        let kind = match token {
+            Token::Str(s) => ExprKind::Literal(Value::String(Cow::Owned(s))),
+            Token::Num(n) => ExprKind::Literal(Value::Number(n)),
+            Token::Ident(i) if i == "true" => ExprKind::Literal(Value::Bool(true)),
+            Token::Ident(i) if i == "false" => ExprKind::Literal(Value::Bool(false)),
+            Token::Ident(i) => match Field::from_name(&i) {
+                Some(field) => ExprKind::Field(field),
+                None => {
+                    let known: Vec<&str> = Field::ALL.iter().map(Field::name).collect();
+                    return Err(ExprError::new(
+                        format!("unknown field `{i}` (expected one of {})", known.join(", ")),
+                        span,
+                    ));
+                }
+            },
+            Token::Op("(") => {
+                let mut node = self.or()?;
+                let end = self.expect(")")?.end;
+                node.span = span.start..end;
+                return Ok(node);
+            }
+            Token::Op("[") => {
+                let mut items = Vec::new();
+                if !self.eat("]") {
+                    loop {
+                        match self.next() {
+                            (Token::Str(s), _) => items.push(s),
+                            (token, span) => {
+                                return Err(ExprError::new(
+                                    format!("expected string, found {}", token.describe()),
+                                    span,
+                                ))
+                            }
+                        }
+                        if self.eat("]") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                let end = self.tokens[self.pos - 1].1.end;
+                return Ok(Node::new(ExprKind::Literal(list(items)), span.start..end));
+            }
+            token => {
+                return Err(ExprError::new(
+                    format!("expected expression, found {}", token.describe()),
+                    span,
+                ))
+            }
+        };
+        Ok(Node::new(kind, span))
+    }
+}
+
+/// Parse an expression. Does not type check it.
+pub(crate) fn parse(src: &str) -> Result<Node, ExprError> {
+    let mut parser = Parser {
+        tokens: lex(src)?,
+        pos: 0,
+    };
+    let node = parser.or()?;
+    if parser.peek() != &Token::Eof {
+        return Err(parser.unexpected("operator or end of expression"));
+    }
+    Ok(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::filter::expr::ast::{CmpOp, ExprKind};
+    use crate::filter::expr::fields::Field;
+
+    #[test]
+    fn test_precedence() {
+        let node = parse("!a_b").unwrap_err();
+        assert_eq!(node.span(), 1..4);
+
+        let node = parse("lang == \"fr\" || prob > 0.5 && !warnings.contains(\"tiny\")").unwrap();
+        match node.kind {
+            ExprKind::Or(lhs, rhs) => {
+                assert!(matches!(lhs.kind, ExprKind::Compare(CmpOp::Eq, _, _)));
+                assert!(matches!(rhs.kind, ExprKind::And(_, _)));
+            }
+            kind => panic!("{kind:?}"),
+        }
+
+        let node = parse("(lang)").unwrap();
+        assert_eq!(node.kind, ExprKind::Field(Field::Lang));
+        assert_eq!(node.span, 0..6);
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases = [
+            (
+                "lang == ",
+                "expected expression, found end of expression",
+                8..8,
+            ),
+            ("lang == \"fr", "unterminated string", 8..11),
+            ("lang = \"fr\"", "unexpected character `=`", 5..6),
+            ("(prob > 1", "expected `)`, found end of expression", 9..9),
+            (
+                "prob > 1 prob",
+                "expected operator or end of expression, found `prob`",
+                9..13,
+            ),
+            (
+                "lang.foo()",
+                "unknown method `foo` (expected contains, starts_with, ends_with or len)",
+                5..8,
+            ),
+            ("lang in [\"fr\", 1]", "expected string, found `1`", 15..16),
+            ("prob > 1.2.3", "invalid number", 7..12),
+        ];
+        for (src, message, span) in cases {
+            let err = parse(src).unwrap_err();
+            assert_eq!(err.message(), message, "{src}");
+            assert_eq!(err.span(), span, "{src}");
+        }
+    }
+}
@@ -0,0 +1,187 @@
+/*! Document filtering
+
+[Filter]s decide which documents to keep, for any [CorpusDocument] version.
+Built-in predicates ([predicates]) can be combined with [FilterOps::and], [FilterOps::or], [FilterOps::not], [All] and [Any],
+and applied on any document stream (such as [crate::v3::Reader] or [crate::oscar_doc::Reader]) with [FilterExt::filter_documents].
+Filters can also be written as text, see [expr].
+
+```
+use oscar_io::filter::{predicates::{Language, Length, MinProb}, FilterExt, FilterOps};
+# use oscar_io::v3::Document;
+# let docs: Vec<Document> = vec![];
+
+let filter = Language::new(["fr", "br"])
+    .and(MinProb(0.8))
+    .and(Length::chars(100..));
+let kept: Vec<Document> = docs
+    .into_iter()
+    .map(Ok)
+    .filter_documents(filter)
+    .collect::<Result<_, _>>()
+    .unwrap();
+```
+!*/
+mod combinators;
+pub mod expr;
+pub mod predicates;
+
+use crate::common::CorpusDocument;
+use crate::error::Error;
+
+pub use combinators::{All, And, Any, FnFilter, Not, Or};
+
+/// Decides if a document should be kept.
+pub trait Filter<D: CorpusDocument> {
+    /// Returns `true` if `doc` should be kept.
+    fn keep(&self, doc: &D) -> bool;
+}
+
+impl<D: CorpusDocument, F: Filter<D> + ?Sized> Filter<D> for Box<F> {
+    fn keep(&self, doc: &D) -> bool {
+        (**self).keep(doc)
+    }
+}
+
+impl<D: CorpusDocument, F: Filter<D> + ?Sized> Filter<D> for &F {
+    fn keep(&self, doc: &D) -> bool {
+        (**self).keep(doc)
+    }
+}
+
+/// Boolean combinators over [Filter]s.
+///
+/// Kept apart from [Filter] so that the document type is inferred from where the filter is used.
+/// Implemented for the filters of this module, custom filters only need an empty `impl`.
+pub trait FilterOps: Sized {
+    /// Keep documents matching both filters.
+    fn and<F>(self, other: F) -> And<Self, F> {
+        And(self, other)
+    }
+
+    /// Keep documents matching any of the filters.
+    fn or<F>(self, other: F) -> Or<Self, F> {
+        Or(self, other)
+    }
+
+    /// Keep documents not matching the filter.
+    fn not(self) -> Not<Self> {
+        Not(self)
+    }
+}
+
+impl<F: ?Sized> FilterOps for Box<F> {}
+
+impl<F: ?Sized> FilterOps for &F {}
+
+/// Use a closure as a [Filter].
+pub fn from_fn<D: CorpusDocument, F: Fn(&D) -> bool>(f: F) -> FnFilter<F> {
+    FnFilter(f)
+}
+
+/// Adds [FilterExt::filter_documents] to document streams.
+pub trait FilterExt<D: CorpusDocument>: Iterator<Item = Result<D, Error>> + Sized {
+    /// Keep documents matching `filter`. Errors are passed through.
+    fn filter_documents<F: Filter<D>>(self, filter: F) -> Filtered<Self, F> {
+        Filtered {
+            documents: self,
+            filter,
+        }
+    }
+}
+
+impl<D: CorpusDocument, I: Iterator<Item = Result<D, Error>>> FilterExt<D> for I {}
+
+/// Iterator adapter returned by [FilterExt::filter_documents].
+pub struct Filtered<I, F> {
+    documents: I,
+    filter: F,
+}
+
+impl<I, D, F> Iterator for Filtered<I, F>
+where
+    I: Iterator<Item = Result<D, Error>>,
+    D: CorpusDocument,
+    F: Filter<D>,
+{
+    type Item = Result<D, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.documents.next()? {
+                Ok(doc) if !self.filter.keep(&doc) => continue,
+                item => return Some(item),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use super::predicates::{HasWarning, Language, MinProb};
+    use super::{from_fn, Filter, FilterExt, FilterOps};
+    use crate::error::Error;
+    use crate::oscar_doc;
+    use crate::test_utils::{doc_in, with_url, with_warnings};
+    use crate::v3::{Document, QualityWarning};
+
+    /// Document in `lang`, with `warnings` and a url.
+    pub(crate) fn doc(content: &str, lang: &str, prob: f32, warnings: &[&str]) -> Document {
+        let doc = with_warnings(doc_in(content, lang, prob), warnings);
+        with_url(doc, "https://www.example.com/page")
+    }
+
+    #[test]
+    fn test_filter_documents() {
+        let docs = vec![
+            Ok(doc("foo", "fr", 0.9, &[])),
+            Err(Error::Custom("oops".to_string())),
+            Ok(doc("bar", "fr", 0.5, &[])),
+            Ok(doc("baz", "fr", 0.95, &["tiny"])),
+        ];
+        let filter = MinProb(0.8).and(HasWarning(QualityWarning::Tiny).not());
+        let kept: Vec<Result<Document, Error>> =
+            docs.into_iter().filter_documents(filter).collect();
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].as_ref().unwrap().content(), "foo");
+        assert!(kept[1].is_err());
+    }
+
+    #[test]
+    fn test_oscar_doc() {
+        let f = File::open("tests/res/data.jsonl").unwrap();
+        let filter = Language::new(["en"]).and(HasWarning(QualityWarning::Header));
+        let kept: Vec<oscar_doc::Document> = oscar_doc::Reader::new(BufReader::new(f))
+            .filter_documents(filter)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(!kept.is_empty());
+        assert!(kept.iter().all(|d| d
+            .metadata()
+            .annotation()
+            .unwrap()
+            .contains(&"header".to_string())));
+    }
+
+    #[test]
+    fn test_combinators() {
+        let d = doc("foo", "fr", 0.9, &["tiny"]);
+        let yes = from_fn(|_: &Document| true);
+        let no = from_fn(|_: &Document| false);
+        assert!((&yes).and(&yes).keep(&d));
+        assert!(!(&yes).and(&no).keep(&d));
+        assert!((&no).or(&yes).keep(&d));
+        assert!(!(&no).or(&no).keep(&d));
+        assert!(no.not().keep(&d));
+
+        let boxed: Vec<Box<dyn Filter<Document>>> = vec![
+            Box::new(MinProb(0.5)),
+            Box::new(HasWarning(QualityWarning::Tiny)),
+        ];
+        assert!(super::All(boxed).keep(&d));
+        assert!(super::Any::default().not().keep(&d));
+        assert!(super::All::default().keep(&d));
+    }
+}
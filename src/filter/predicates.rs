@@ -0,0 +1,246 @@
+//! Built-in [Filter]s.
+use std::collections::HashSet;
+use std::ops::{Bound, RangeBounds};
+
+use crate::common::CorpusDocument;
+use crate::v3::{Category, QualityWarning};
+
+use super::{Filter, FilterOps};
+
+fn bounds<T: Copy>(range: impl RangeBounds<T>) -> (Bound<T>, Bound<T>) {
+    (range.start_bound().cloned(), range.end_bound().cloned())
+}
+
+/// Keeps documents identified as one of the provided languages (ex. `"fr"`).
+#[derive(Debug, Clone)]
+pub struct Language(HashSet<String>);
+
+impl Language {
+    pub fn new<S: Into<String>>(langs: impl IntoIterator<Item = S>) -> Self {
+        Self(langs.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<D: CorpusDocument> Filter<D> for Language {
+    fn keep(&self, doc: &D) -> bool {
+        self.0.contains(doc.identification().label().as_str())
+    }
+}
+
+impl FilterOps for Language {}
+
+/// Keeps documents whose identification probability is at least the provided one.
+#[derive(Debug, Clone, Copy)]
+pub struct MinProb(pub f32);
+
+impl<D: CorpusDocument> Filter<D> for MinProb {
+    fn keep(&self, doc: &D) -> bool {
+        *doc.identification().prob() >= self.0
+    }
+}
+
+impl FilterOps for MinProb {}
+
+/// Keeps documents whose harmful perplexity is in the provided range (ex. `..=1000.0`).
+/// Documents without harmful perplexity are removed.
+#[derive(Debug, Clone, Copy)]
+pub struct HarmfulPp((Bound<f32>, Bound<f32>));
+
+impl HarmfulPp {
+    pub fn new(range: impl RangeBounds<f32>) -> Self {
+        Self(bounds(range))
+    }
+}
+
+impl<D: CorpusDocument> Filter<D> for HarmfulPp {
+    fn keep(&self, doc: &D) -> bool {
+        doc.harmful_pp().is_some_and(|pp| self.0.contains(&pp))
+    }
+}
+
+impl FilterOps for HarmfulPp {}
+
+/// Keeps documents having the provided quality warning (annotation in OSCAR 22.01).
+/// Use [FilterOps::not] to remove them instead.
+#[derive(Debug, Clone)]
+pub struct HasWarning(pub QualityWarning);
+
+impl<D: CorpusDocument> Filter<D> for HasWarning {
+    fn keep(&self, doc: &D) -> bool {
+        doc.annotations().contains(&self.0.as_str())
+    }
+}
+
+impl FilterOps for HasWarning {}
+
+/// Removes documents having at least one of the provided categories.
+#[derive(Debug, Clone)]
+pub struct CategoryBlocklist(HashSet<Category>);
+
+impl CategoryBlocklist {
+    pub fn new<C: Into<Category>>(categories: impl IntoIterator<Item = C>) -> Self {
+        Self(categories.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<D: CorpusDocument> Filter<D> for CategoryBlocklist {
+    fn keep(&self, doc: &D) -> bool {
+        !doc.categories()
+            .into_iter()
+            .any(|category| self.0.contains(&Category::from(category)))
+    }
+}
+
+impl FilterOps for CategoryBlocklist {}
+
+/// Keeps (allowlist) or removes (denylist) documents from the provided domains, subdomains included.
+///
+/// Documents without a valid URL are removed by allowlists and kept by denylists.
+#[derive(Debug, Clone)]
+pub struct Domains {
+    domains: HashSet<String>,
+    allow: bool,
+}
+
+impl Domains {
+    /// Keep only documents from `domains`.
+    pub fn allow<S: Into<String>>(domains: impl IntoIterator<Item = S>) -> Self {
+        Self::new(domains, true)
+    }
+
+    /// Remove documents from `domains`.
+    pub fn deny<S: Into<String>>(domains: impl IntoIterator<Item = S>) -> Self {
+        Self::new(domains, false)
+    }
+
+    fn new<S: Into<String>>(domains: impl IntoIterator<Item = S>, allow: bool) -> Self {
+        Self {
+            domains: domains
+                .into_iter()
+                .map(|d| d.into().to_lowercase())
+                .collect(),
+            allow,
+        }
+    }
+
+    /// Checks if `host` or one of its parent domains is in the list.
+    fn matches(&self, host: &str) -> bool {
+        let mut host = host;
+        loop {
+            if self.domains.contains(host) {
+                return true;
+            }
+            match host.split_once('.') {
+                Some((_, parent)) => host = parent,
+                None => return false,
+            }
+        }
+    }
+}
+
+impl<D: CorpusDocument> Filter<D> for Domains {
+    fn keep(&self, doc: &D) -> bool {
+        match doc.host() {
+            Some(host) => self.matches(&host) == self.allow,
+            None => !self.allow,
+        }
+    }
+}
+
+impl FilterOps for Domains {}
+
+/// Keeps documents whose content length is in the provided range.
+#[derive(Debug, Clone, Copy)]
+pub struct Length {
+    range: (Bound<usize>, Bound<usize>),
+    chars: bool,
+}
+
+impl Length {
+    /// Length in bytes.
+    pub fn bytes(range: impl RangeBounds<usize>) -> Self {
+        Self {
+            range: bounds(range),
+            chars: false,
+        }
+    }
+
+    /// Length in characters.
+    pub fn chars(range: impl RangeBounds<usize>) -> Self {
+        Self {
+            range: bounds(range),
+            chars: true,
+        }
+    }
+}
+
+impl<D: CorpusDocument> Filter<D> for Length {
+    fn keep(&self, doc: &D) -> bool {
+        let len = if self.chars {
+            doc.content().chars().count()
+        } else {
+            doc.content().len()
+        };
+        self.range.contains(&len)
+    }
+}
+
+impl FilterOps for Length {}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use super::{CategoryBlocklist, Domains, HarmfulPp, HasWarning, Language, Length, MinProb};
+    use crate::filter::tests::doc;
+    use crate::filter::Filter;
+    use crate::v3::{Category, QualityWarning};
+
+    #[test]
+    fn test_identification() {
+        let d = doc("foo", "fr", 0.8, &[]);
+        assert!(Language::new(["fr", "br"]).keep(&d));
+        assert!(!Language::new(["en"]).keep(&d));
+        assert!(MinProb(0.8).keep(&d));
+        assert!(!MinProb(0.81).keep(&d));
+    }
+
+    #[test]
+    fn test_metadata() {
+        let mut d = doc("foo", "fr", 0.8, &["tiny", "noisy"]);
+        assert!(HasWarning(QualityWarning::Tiny).keep(&d));
+        assert!(!HasWarning(QualityWarning::Header).keep(&d));
+
+        assert!(!HarmfulPp::new(..).keep(&d));
+        d.metadata_mut().set_harmful_pp(Some(500.0));
+        assert!(HarmfulPp::new(..=500.0).keep(&d));
+        assert!(!HarmfulPp::new(100.0..500.0).keep(&d));
+
+        let blocklist = CategoryBlocklist::new(["adult", "gambling"]);
+        assert!(blocklist.keep(&d));
+        d.metadata_mut().insert_category(Category::Gambling);
+        assert!(!blocklist.keep(&d));
+    }
+
+    #[test]
+    fn test_domains() {
+        let mut d = doc("foo", "fr", 0.8, &[]);
+        assert!(Domains::allow(["example.com"]).keep(&d));
+        assert!(!Domains::allow(["www2.example.com", "other.com"]).keep(&d));
+        assert!(!Domains::deny(["Example.com"]).keep(&d));
+        assert!(Domains::deny(["ample.com"]).keep(&d));
+
+        d.warc_headers_mut()
+            .set_target_uri(&Url::parse("file:///tmp/foo").unwrap());
+        assert!(!Domains::allow(["example.com"]).keep(&d));
+        assert!(Domains::deny(["example.com"]).keep(&d));
+    }
+
+    #[test]
+    fn test_length() {
+        let d = doc("héhé", "fr", 0.8, &[]);
+        assert!(Length::chars(4..=4).keep(&d));
+        assert!(Length::bytes(6..).keep(&d));
+        assert!(!Length::bytes(..6).keep(&d));
+    }
+}
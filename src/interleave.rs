@@ -0,0 +1,352 @@
+/*! Interleaving of document streams
+
+Combinators over several streams of `Result<D, Error>` (readers, for example):
+
+- [RoundRobin] takes one document of each stream in turn,
+- [Weighted] takes documents proportionally to weights (language ratios, for example),
+- [SortedMerge] merges streams already sorted by a key into a sorted stream (see [crate::sort] to sort them).
+
+Streams can be tagged with [SourceExt::tag_source] beforehand, so that output documents record their input
+in the [SOURCE_KEY] extension.
+
+```
+use oscar_io::interleave::{RoundRobin, SourceExt, SOURCE_KEY};
+# use oscar_io::v3::Document;
+# let (a, b): (Vec<Document>, Vec<Document>) = (vec![], vec![]);
+
+let merged: Vec<Document> = RoundRobin::new([
+    a.into_iter().map(Ok).tag_source("crawl-a"),
+    b.into_iter().map(Ok).tag_source("crawl-b"),
+])
+.collect::<Result<_, _>>()
+.unwrap();
+```
+
+Errors are forwarded as they come, and streams are read again afterwards: stop at the first error to avoid that.
+!*/
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use serde_json::Value;
+
+use crate::common::CorpusDocument;
+use crate::error::Error;
+
+/// Extension key where [Tagged] records the source of documents.
+pub const SOURCE_KEY: &str = "source";
+
+/// Records the source name of each document in the [SOURCE_KEY] extension.
+pub struct Tagged<I> {
+    inner: I,
+    source: String,
+}
+
+impl<I, D> Iterator for Tagged<I>
+where
+    I: Iterator<Item = Result<D, Error>>,
+    D: CorpusDocument,
+{
+    type Item = Result<D, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|doc| {
+            doc.map(|mut doc| {
+                doc.extensions_mut()
+                    .set_raw(SOURCE_KEY, Value::String(self.source.clone()));
+                doc
+            })
+        })
+    }
+}
+
+/// Tag the documents of a stream with their source.
+pub trait SourceExt<D>: Iterator<Item = Result<D, Error>> + Sized {
+    fn tag_source(self, source: impl Into<String>) -> Tagged<Self> {
+        Tagged {
+            inner: self,
+            source: source.into(),
+        }
+    }
+}
+
+impl<I, D> SourceExt<D> for I where I: Iterator<Item = Result<D, Error>> {}
+
+/// Takes one document of each stream in turn, skipping exhausted streams.
+pub struct RoundRobin<I> {
+    sources: Vec<I>,
+    next: usize,
+}
+
+impl<I> RoundRobin<I> {
+    pub fn new(sources: impl IntoIterator<Item = I>) -> Self {
+        Self {
+            sources: sources.into_iter().collect(),
+            next: 0,
+        }
+    }
+}
+
+impl<I, D> Iterator for RoundRobin<I>
+where
+    I: Iterator<Item = Result<D, Error>>,
+{
+    type Item = Result<D, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.sources.is_empty() {
+            let idx = self.next % self.sources.len();
+            match self.sources[idx].next() {
+                Some(doc) => {
+                    self.next = idx + 1;
+                    return Some(doc);
+                }
+                // the following stream takes its place
+                None => {
+                    self.sources.remove(idx);
+                    self.next = idx;
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Takes documents from streams proportionally to their weights.
+///
+/// Streams are chosen with smooth weighted round-robin: the output is deterministic,
+/// and streams are spread evenly (weights `2` and `1` give `a, b, a, a, b, a...`).
+/// When a stream is exhausted, the other ones go on with their weights.
+pub struct Weighted<I> {
+    sources: Vec<(I, f64, f64)>,
+}
+
+impl<I> Weighted<I> {
+    /// Streams with a weight of zero or less are never read.
+    pub fn new(sources: impl IntoIterator<Item = (I, f64)>) -> Self {
+        Self {
+            sources: sources
+                .into_iter()
+                .filter(|(_, weight)| *weight > 0.0)
+                .map(|(source, weight)| (source, weight, 0.0))
+                .collect(),
+        }
+    }
+}
+
+impl<I, D> Iterator for Weighted<I>
+where
+    I: Iterator<Item = Result<D, Error>>,
+{
+    type Item = Result<D, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.sources.is_empty() {
+            let total: f64 = self.sources.iter().map(|(_, weight, _)| weight).sum();
+            for (_, weight, current) in self.sources.iter_mut() {
+                *current += *weight;
+            }
+            let mut chosen = 0;
+            for idx in 1..self.sources.len() {
+                if self.sources[idx].2 > self.sources[chosen].2 {
+                    chosen = idx;
+                }
+            }
+            self.sources[chosen].2 -= total;
+            match self.sources[chosen].0.next() {
+                Some(doc) => return Some(doc),
+                None => {
+                    self.sources.remove(chosen);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Merges streams sorted by key into a sorted stream.
+///
+/// Documents with equal keys are taken from the first stream first.
+/// Output is only sorted if every stream is.
+///
+/// At most one error is read per call: a stream yielding an error is read again on the next call.
+pub struct SortedMerge<I, D, K, F> {
+    sources: Vec<I>,
+    key: F,
+    heads: Vec<Option<D>>,
+    heap: BinaryHeap<Reverse<(K, usize)>>,
+    errors: VecDeque<Error>,
+    // streams to read before taking the smallest head
+    pending: Vec<usize>,
+}
+
+impl<I, D, K, F> SortedMerge<I, D, K, F>
+where
+    I: Iterator<Item = Result<D, Error>>,
+    F: Fn(&D) -> K,
+    K: Ord,
+{
+    pub fn new(sources: impl IntoIterator<Item = I>, key: F) -> Self {
+        let sources: Vec<I> = sources.into_iter().collect();
+        Self {
+            heads: sources.iter().map(|_| None).collect(),
+            heap: BinaryHeap::with_capacity(sources.len()),
+            pending: (0..sources.len()).collect(),
+            sources,
+            key,
+            errors: VecDeque::new(),
+        }
+    }
+
+    /// Read the next document of a stream. Errors are queued, and the stream stays pending.
+    fn fill(&mut self, idx: usize) {
+        match self.sources[idx].next() {
+            Some(Ok(doc)) => {
+                self.heap.push(Reverse(((self.key)(&doc), idx)));
+                self.heads[idx] = Some(doc);
+            }
+            Some(Err(e)) => {
+                self.errors.push_back(e);
+                self.pending.push(idx);
+            }
+            None => (),
+        }
+    }
+}
+
+impl<I, D, K, F> Iterator for SortedMerge<I, D, K, F>
+where
+    I: Iterator<Item = Result<D, Error>>,
+    F: Fn(&D) -> K,
+    K: Ord,
+{
+    type Item = Result<D, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(e) = self.errors.pop_front() {
+                return Some(Err(e));
+            }
+            if self.pending.is_empty() {
+                break;
+            }
+            for idx in std::mem::take(&mut self.pending) {
+                self.fill(idx);
+            }
+        }
+        let Reverse((_, idx)) = self.heap.pop()?;
+        let doc = self.heads[idx]
+            .take()
+            .expect("streams in the heap have a document");
+        self.pending.push(idx);
+        Some(Ok(doc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RoundRobin, SortedMerge, SourceExt, Weighted, SOURCE_KEY};
+    use crate::error::Error;
+    use crate::test_utils::doc;
+    use crate::v3::Document;
+
+    fn stream(contents: &[&str]) -> std::vec::IntoIter<Result<Document, Error>> {
+        contents
+            .iter()
+            .map(|c| Ok(doc(*c)))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn contents(docs: impl Iterator<Item = Result<Document, Error>>) -> Vec<String> {
+        docs.map(|d| d.unwrap().content().clone()).collect()
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let merged = RoundRobin::new([
+            stream(&["a1", "a2", "a3", "a4"]),
+            stream(&[]),
+            stream(&["c1"]),
+            stream(&["d1", "d2"]),
+        ]);
+        assert_eq!(
+            contents(merged),
+            vec!["a1", "c1", "d1", "a2", "d2", "a3", "a4"]
+        );
+    }
+
+    #[test]
+    fn test_weighted() {
+        let merged = Weighted::new([
+            (stream(&["a1", "a2", "a3", "a4", "a5"]), 2.0),
+            (stream(&["b1", "b2", "b3"]), 1.0),
+            (stream(&["c1"]), 0.0),
+        ]);
+        assert_eq!(
+            contents(merged),
+            vec!["a1", "b1", "a2", "a3", "b2", "a4", "a5", "b3"]
+        );
+    }
+
+    #[test]
+    fn test_sorted_merge() {
+        let merged = SortedMerge::new(
+            [
+                stream(&["a", "c", "e"]).tag_source("first"),
+                stream(&["b", "c", "f"]).tag_source("second"),
+                stream(&["d"]).tag_source("third"),
+            ],
+            |d: &Document| d.content().clone(),
+        );
+        let docs: Vec<Document> = merged.collect::<Result<_, _>>().unwrap();
+        let sources: Vec<(String, String)> = docs
+            .iter()
+            .map(|d| {
+                (
+                    d.content().clone(),
+                    d.metadata().get_extension(SOURCE_KEY).unwrap().unwrap(),
+                )
+            })
+            .collect();
+        let expected = [
+            ("a", "first"),
+            ("b", "second"),
+            ("c", "first"),
+            ("c", "second"),
+            ("d", "third"),
+            ("e", "first"),
+            ("f", "second"),
+        ];
+        assert_eq!(
+            sources,
+            expected
+                .iter()
+                .map(|(c, s)| (c.to_string(), s.to_string()))
+                .collect::<Vec<_>>()
+        );
+
+        // errors are forwarded as soon as they are read
+        let failing = vec![
+            Ok(doc("a")),
+            Err(Error::Custom("oops".to_string())),
+            Ok(doc("b")),
+        ];
+        let mut merged = SortedMerge::new([failing.into_iter(), stream(&["c"])], |d: &Document| {
+            d.content().clone()
+        });
+        assert_eq!(merged.next().unwrap().unwrap().content(), "a");
+        assert!(merged.next().unwrap().is_err());
+        assert_eq!(contents(merged), vec!["b", "c"]);
+
+        // a stream failing forever yields one error per call
+        let failing = std::iter::repeat_with(|| Err(Error::Custom("oops".to_string())));
+        let mut merged = SortedMerge::new(
+            [
+                Box::new(failing) as Box<dyn Iterator<Item = Result<Document, Error>>>,
+                Box::new(stream(&["a"])),
+            ],
+            |d: &Document| d.content().clone(),
+        );
+        assert!(merged.by_ref().take(3).all(|doc| doc.is_err()));
+    }
+}
@@ -1,9 +1,22 @@
 #![doc = include_str!("../README.md")]
 pub mod common;
+pub mod dedup;
+pub mod diff;
 pub mod error;
+pub mod filter;
+pub mod interleave;
 pub mod lang;
 pub mod oscar_doc;
+pub mod sample;
+pub mod schema;
+pub mod sort;
+pub mod split;
+pub mod stats;
+#[cfg(test)]
+mod test_utils;
+pub mod transform;
 
 pub mod v3;
+pub mod warc_io;
 
 pub use error::Error;
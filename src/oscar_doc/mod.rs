@@ -5,6 +5,7 @@ mod reader;
 mod types;
 mod writer;
 
+pub use crate::common::WarcHeaders;
 #[cfg(feature = "avro")]
 pub use reader::AvroDocReader as AvroReader;
 pub use reader::DocReader as Reader;
@@ -12,5 +13,4 @@ pub use reader::SplitFileIter as SplitReader;
 pub use reader::SplitFolderFileIter as SplitFolderReader;
 pub use types::Document;
 pub use types::Metadata;
-pub use types::WarcHeaders;
 pub use writer::DocWriter as Writer;
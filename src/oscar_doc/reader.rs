@@ -8,6 +8,8 @@
 use avro_rs::Reader;
 use flate2::bufread::MultiGzDecoder;
 use log::info;
+#[cfg(feature = "avro")]
+use std::io::Read;
 use std::{
     fs::File,
     io::{BufRead, BufReader},
@@ -296,10 +298,10 @@ impl Iterator for SplitFolderFileIter {
                     // TODO: remove potential infinite recursion
                     Some(Ok(())) => self.next(),
                     Some(Err(e)) => Some(Err(e)),
-                    None => None
                     // None => Some(Err(Error::Custom(
                     //     "Something went wrong when trying to open the first file..".to_string(),
                     // ))),
+                    None => None,
                 }
             }
 
@@ -350,7 +352,7 @@ mod tests {
         let content = r#"{"foo": "bar"}"#;
         let mut r = DocReader::new(content.as_bytes());
         match r.next() {
-            Some(Err(Error::SerdeJson(_))) => assert!(true),
+            Some(Err(Error::SerdeJson(_))) => (),
             x => panic!("wrong return: {:?}", x),
         }
     }
@@ -368,7 +370,7 @@ mod tests {
         let mut compressed_content = vec![];
         {
             let mut enc = GzEncoder::new(&mut compressed_content, Compression::fast());
-            enc.write(content.as_bytes()).unwrap();
+            enc.write_all(content.as_bytes()).unwrap();
         }
 
         let c = Cursor::new(&mut compressed_content);
@@ -1,13 +1,18 @@
+use std::borrow::Cow;
+
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::common::Identification;
+use warc::WarcHeader;
+
+use crate::common::{CorpusDocument, Extensions, Identification, WarcHeaders};
 
-use super::{Metadata, WarcHeaders};
+use super::Metadata;
 
 /// A Document is a structure holding content, WARC headers and OSCAR-specific metadata.
 /// - TODO: Change warc_headers from [RawRecordHeader] to [warc::Record] with [warc::EmptyBody]?
-/// This way we shouldn't have to parse strings or use unwrap on [RawRecordHeader].
-#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+///   This way we shouldn't have to parse strings or use unwrap on [RawRecordHeader].
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
 pub struct Document {
     content: String,
     warc_headers: WarcHeaders,
@@ -23,7 +28,10 @@ impl Document {
         }
     }
 
-    /// Instantiate a Document from a record and a related metadata.
+    /// Get the JSON Schema of serialized documents, pretty-printed.
+    pub fn get_schema() -> Result<String, crate::error::Error> {
+        crate::schema::Format::OscarDoc.to_json()
+    }
 
     /// Get a reference to the Document's identification
     pub fn identification(&self) -> &Identification<String> {
@@ -50,13 +58,91 @@ impl Document {
         &self.metadata
     }
 
-    /// Set the document's content.
+    /// Set the document's content, and its `content-length` WARC header if present.
     pub fn set_content(&mut self, content: String) {
+        if self.warc_headers.contains_key(&WarcHeader::ContentLength) {
+            self.warc_headers.set_content_length(content.len() as u64);
+        }
         self.content = content;
     }
 }
 
+impl CorpusDocument for Document {
+    fn content(&self) -> &str {
+        &self.content
+    }
+
+    fn set_content(&mut self, content: String) {
+        Document::set_content(self, content);
+    }
+
+    fn identification(&self) -> &Identification<String> {
+        self.metadata.identification()
+    }
+
+    fn set_identification(&mut self, identification: Identification<String>) {
+        self.metadata.set_identification(identification);
+    }
+
+    fn sentence_identifications(&self) -> &[Option<Identification<String>>] {
+        self.metadata.sentence_identifications()
+    }
+
+    fn set_sentence_identifications(
+        &mut self,
+        sentence_identifications: Vec<Option<Identification<String>>>,
+    ) {
+        self.metadata
+            .set_sentence_identifications(sentence_identifications);
+    }
+
+    fn annotations(&self) -> Vec<&str> {
+        self.metadata
+            .annotation()
+            .map(|annotations| annotations.iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    fn url(&self) -> Option<Cow<'_, str>> {
+        self.warc_headers
+            .get(&WarcHeader::TargetURI)
+            .map(|url| String::from_utf8_lossy(url))
+    }
+
+    fn record_id(&self) -> Option<Cow<'_, str>> {
+        self.warc_headers
+            .get(&WarcHeader::RecordID)
+            .map(|id| String::from_utf8_lossy(id))
+    }
+
+    fn extensions(&self) -> &Extensions {
+        self.metadata.extensions()
+    }
+
+    fn extensions_mut(&mut self) -> &mut Extensions {
+        self.metadata.extensions_mut()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    // TODO
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    use super::Document;
+
+    #[test]
+    fn test_format_unchanged() {
+        let f = File::open("tests/res/data.jsonl").unwrap();
+        for line in BufReader::new(f).lines() {
+            let line = line.unwrap();
+            let doc: Document = serde_json::from_str(&line).unwrap();
+
+            let expected: serde_json::Value = serde_json::from_str(&line).unwrap();
+            // go through a string, f32 probabilities would be widened by to_value
+            let serialized: serde_json::Value =
+                serde_json::from_str(&serde_json::to_string(&doc).unwrap()).unwrap();
+            assert_eq!(serialized, expected);
+        }
+    }
 }
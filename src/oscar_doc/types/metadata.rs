@@ -1,15 +1,20 @@
 use oxilangtag::LanguageTag;
-use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::common::Identification;
+use crate::common::{Extensions, Identification};
+use crate::error::Error;
 
 /// OSCAR Metadata.
 /// Contains document identification, annotations and sentence-level identifications.
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+/// Unknown fields are kept in [Extensions].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct Metadata {
     identification: Identification<String>,
     annotation: Option<Vec<String>>,
     sentence_identifications: Vec<Option<Identification<String>>>,
+    #[serde(flatten)]
+    extensions: Extensions,
 }
 
 impl Metadata {
@@ -24,6 +29,7 @@ impl Metadata {
             identification: identification.clone(),
             annotation: annotation.to_owned(),
             sentence_identifications: sentence_identifications.to_owned(),
+            extensions: Extensions::default(),
         }
     }
 
@@ -40,10 +46,52 @@ impl Metadata {
         &self.identification
     }
 
+    /// Set the document-level identification.
+    pub fn set_identification(&mut self, identification: Identification<String>) {
+        self.identification = identification;
+    }
+
     /// Get a reference to the metadata's annotation.
     pub fn annotation(&self) -> Option<&Vec<String>> {
         self.annotation.as_ref()
     }
+
+    /// Get a reference to the metadata's sentence identifications.
+    pub fn sentence_identifications(&self) -> &[Option<Identification<String>>] {
+        &self.sentence_identifications
+    }
+
+    /// Set the metadata's sentence identifications.
+    pub fn set_sentence_identifications(
+        &mut self,
+        sentence_identifications: Vec<Option<Identification<String>>>,
+    ) {
+        self.sentence_identifications = sentence_identifications;
+    }
+
+    /// Get a reference to the unknown fields.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Get a mutable reference to the unknown fields.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Get an unknown field, deserialized as `T`.
+    pub fn get_extension<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Error> {
+        self.extensions.get(key)
+    }
+
+    /// Set an unknown field.
+    pub fn set_extension<T: Serialize>(
+        &mut self,
+        key: impl Into<String>,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.extensions.set(key, value)
+    }
 }
 
 impl Default for Metadata {
@@ -55,6 +103,7 @@ impl Default for Metadata {
             identification: Identification::new(default_tag.clone(), 1.0),
             annotation: None,
             sentence_identifications: vec![Some(Identification::new(default_tag, 1.0))],
+            extensions: Extensions::default(),
         }
     }
 }
@@ -82,8 +82,8 @@ mod tests {
         // map results to Ok, crashing if Error
         let docs_from_reader: Vec<Document> = reader.map(|x| x.unwrap()).collect();
 
-        assert!(docs.len() != 0);
-        assert!(docs_from_reader.len() != 0);
+        assert!(!docs.is_empty());
+        assert!(!docs_from_reader.is_empty());
         assert_eq!(docs, docs_from_reader);
     }
 
@@ -105,8 +105,8 @@ mod tests {
         // map results to Ok, crashing if Error
         let docs_from_reader: Vec<Document> = reader.map(|x| x.unwrap()).collect();
 
-        assert!(docs.len() != 0);
-        assert!(docs_from_reader.len() != 0);
+        assert!(!docs.is_empty());
+        assert!(!docs_from_reader.is_empty());
         assert_eq!(docs, docs_from_reader);
     }
 }
@@ -0,0 +1,31 @@
+/*! Reproducible sampling
+
+- [Reservoir] uniformly samples `n` documents in a single pass,
+- [StratifiedSampler] samples `n` documents per language, domain or quality warning ([Stratum]),
+- [TwoPassSampler] uniformly samples `n` documents from files whose document counts are known, streaming them in corpus order,
+- [ExternalShuffle] shuffles files too large to fit in memory, using temporary buckets on disk.
+
+Every sampler is seeded: the same seed and corpus always give the same sample (or order).
+
+```
+use std::fs::File;
+use std::io::BufReader;
+
+use oscar_io::oscar_doc;
+use oscar_io::sample::Reservoir;
+
+let f = File::open("tests/res/data.jsonl").unwrap();
+let sample = Reservoir::sample(oscar_doc::Reader::new(BufReader::new(f)), 10, 42).unwrap();
+assert_eq!(sample.len(), 10);
+```
+!*/
+mod reservoir;
+mod rng;
+mod shuffle;
+mod stratified;
+mod two_pass;
+
+pub use reservoir::Reservoir;
+pub use shuffle::ExternalShuffle;
+pub use stratified::{StratifiedSampler, Stratum, NO_STRATUM};
+pub use two_pass::TwoPassSampler;
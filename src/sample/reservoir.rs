@@ -0,0 +1,110 @@
+//! Uniform reservoir sampling.
+use crate::error::Error;
+
+use super::rng::Rng;
+
+/// Uniformly samples up to `capacity` items from a stream of unknown length, in a single pass.
+///
+/// Samples only depend on the seed and the order of the items.
+/// They are returned in stream order.
+#[derive(Debug, Clone)]
+pub struct Reservoir<T> {
+    capacity: usize,
+    seen: u64,
+    items: Vec<(u64, T)>,
+    rng: Rng,
+}
+
+impl<T> Reservoir<T> {
+    pub fn new(capacity: usize, seed: u64) -> Self {
+        Self::with_rng(capacity, Rng::new(seed))
+    }
+
+    pub(crate) fn with_rng(capacity: usize, rng: Rng) -> Self {
+        Self {
+            capacity,
+            seen: 0,
+            // grows with the stream: capacity can be much larger than the number of items
+            items: Vec::new(),
+            rng,
+        }
+    }
+
+    /// Offer an item to the reservoir.
+    pub fn add(&mut self, item: T) {
+        let idx = self.seen;
+        self.seen += 1;
+        if self.items.len() < self.capacity {
+            self.items.push((idx, item));
+        } else {
+            let j = self.rng.below(self.seen) as usize;
+            if j < self.capacity {
+                self.items[j] = (idx, item);
+            }
+        }
+    }
+
+    /// Offer every item of a stream (for example a file), stopping at the first error.
+    ///
+    /// Can be called once per file of a corpus.
+    pub fn add_all<I>(&mut self, items: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = Result<T, Error>>,
+    {
+        for item in items {
+            self.add(item?);
+        }
+        Ok(())
+    }
+
+    /// Number of items offered so far.
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    /// Get the sampled items, in stream order.
+    pub fn into_items(mut self) -> Vec<T> {
+        self.items.sort_unstable_by_key(|(idx, _)| *idx);
+        self.items.into_iter().map(|(_, item)| item).collect()
+    }
+
+    /// Sample `n` items of a stream.
+    pub fn sample<I>(items: I, n: usize, seed: u64) -> Result<Vec<T>, Error>
+    where
+        I: IntoIterator<Item = Result<T, Error>>,
+    {
+        let mut reservoir = Self::new(n, seed);
+        reservoir.add_all(items)?;
+        Ok(reservoir.into_items())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Reservoir;
+
+    #[test]
+    fn test_sample() {
+        let items = || (0..1000u32).map(Ok);
+        let sample = Reservoir::sample(items(), 10, 42).unwrap();
+        assert_eq!(sample.len(), 10);
+        assert!(sample.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(Reservoir::sample(items(), 10, 42).unwrap(), sample);
+        assert_ne!(Reservoir::sample(items(), 10, 43).unwrap(), sample);
+
+        let all = Reservoir::sample((0..5u32).map(Ok), 10, 42).unwrap();
+        assert_eq!(all, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_uniform() {
+        // each item should be sampled ~ 2000 * 10 / 100 = 200 times
+        let mut counts = [0u32; 100];
+        for seed in 0..2000 {
+            for i in Reservoir::sample((0..100usize).map(Ok), 10, seed).unwrap() {
+                counts[i] += 1;
+            }
+        }
+        assert!(counts.iter().all(|c| (130..270).contains(c)), "{counts:?}");
+    }
+}
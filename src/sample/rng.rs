@@ -0,0 +1,56 @@
+//! Small seeded random number generator.
+//!
+//! Samples must be reproducible across releases, so we don't depend on the algorithm of an external crate.
+use crate::dedup::hash;
+
+/// splitmix64 generator.
+#[derive(Debug, Clone)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Generator for a named stream derived from `seed`, independent of the order streams are created in.
+    pub(crate) fn derive(seed: u64, name: &str) -> Self {
+        Self(seed ^ hash(name.as_bytes()))
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform integer in `0..n`, without modulo bias.
+    pub(crate) fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "empty range");
+        let zone = u64::MAX - (u64::MAX % n);
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+
+    #[test]
+    fn test_stable() {
+        // samples must never change for a given seed
+        let mut rng = Rng::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert!((0..1000).all(|_| rng.below(10) < 10));
+        assert_ne!(
+            Rng::derive(0, "fr").next_u64(),
+            Rng::derive(0, "en").next_u64()
+        );
+    }
+}
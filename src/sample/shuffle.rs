@@ -0,0 +1,174 @@
+//! Disk-backed global shuffle.
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::common::tmp::TmpDir;
+use crate::error::Error;
+use crate::v3::WriterTrait;
+
+use super::rng::Rng;
+
+/// Shuffles documents from several files with bounded memory.
+///
+/// The first pass scatters documents into `buckets` temporary files, choosing a bucket at random for each document.
+/// The second pass loads each bucket in memory, shuffles it and sends its documents to a writer
+/// (a rotating [crate::v3::Writer], for example).
+/// Memory usage is about the size of the largest bucket: use roughly `corpus size / available memory` buckets.
+///
+/// The output order only depends on the seed, the number of buckets and the input order.
+#[derive(Debug, Clone)]
+pub struct ExternalShuffle {
+    buckets: usize,
+    seed: u64,
+    tmp_dir: Option<PathBuf>,
+}
+
+impl ExternalShuffle {
+    pub fn new(buckets: usize, seed: u64) -> Self {
+        Self {
+            buckets: buckets.max(1),
+            seed,
+            tmp_dir: None,
+        }
+    }
+
+    /// Directory in which temporary buckets are created. Defaults to [std::env::temp_dir].
+    pub fn with_tmp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.tmp_dir = Some(dir.into());
+        self
+    }
+
+    /// Shuffle the documents of `paths` into `writer`.
+    ///
+    /// Temporary buckets are removed afterwards, even on errors. The writer is not flushed.
+    /// Returns the number of documents written.
+    pub fn shuffle<P, F, I, D, W>(&self, paths: &[P], open: F, writer: &mut W) -> Result<u64, Error>
+    where
+        P: AsRef<Path>,
+        F: Fn(&Path) -> Result<I, Error>,
+        I: IntoIterator<Item = Result<D, Error>>,
+        D: Serialize + DeserializeOwned,
+        W: WriterTrait<Item = D>,
+    {
+        let tmp = TmpDir::create(self.tmp_dir.as_deref(), "shuffle")?;
+        let buckets: Vec<PathBuf> = (0..self.buckets)
+            .map(|idx| tmp.path().join(format!("bucket-{idx}.jsonl")))
+            .collect();
+
+        // scatter
+        let mut rng = Rng::derive(self.seed, "scatter");
+        let mut files = buckets
+            .iter()
+            .map(|path| Ok(BufWriter::new(File::create(path)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let count = files.len() as u64;
+        for path in paths {
+            for doc in open(path.as_ref())? {
+                let file = &mut files[rng.below(count) as usize];
+                serde_json::to_writer(&mut *file, &doc?)?;
+                file.write_all(b"\n")?;
+            }
+        }
+        files.into_iter().try_for_each(|mut file| file.flush())?;
+
+        // shuffle each bucket
+        let mut written = 0;
+        for (idx, path) in buckets.iter().enumerate() {
+            let mut lines = BufReader::new(File::open(path)?)
+                .lines()
+                .collect::<Result<Vec<_>, _>>()?;
+            let mut rng = Rng::derive(self.seed, &format!("bucket-{idx}"));
+            // Fisher-Yates
+            for i in (1..lines.len()).rev() {
+                let j = rng.below(i as u64 + 1) as usize;
+                lines.swap(i, j);
+            }
+            for line in lines {
+                writer.write(vec![serde_json::from_str(&line)?])?;
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+    use std::path::Path;
+
+    use oxilangtag::LanguageTag;
+
+    use super::ExternalShuffle;
+    use crate::error::Error;
+    use crate::test_utils::doc;
+    use crate::v3::{self, Document, WriterTrait};
+
+    fn docs(file: usize) -> Vec<Document> {
+        (0..100)
+            .map(|i| doc(format!("file {file} doc {i}")))
+            .collect()
+    }
+
+    fn open(path: &Path) -> Result<impl Iterator<Item = Result<Document, Error>>, Error> {
+        let file: usize = path.to_str().unwrap().parse().unwrap();
+        Ok(docs(file).into_iter().map(Ok))
+    }
+
+    fn shuffle(seed: u64, dst: &Path, tmp: &Path) -> Vec<String> {
+        let mut writer = v3::Writer::new(
+            dst,
+            LanguageTag::parse("en".to_string()).unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+        let n = ExternalShuffle::new(4, seed)
+            .with_tmp_dir(tmp)
+            .shuffle(&["0", "1", "2"], open, &mut writer)
+            .unwrap();
+        assert_eq!(n, 300);
+        writer.flush().unwrap();
+
+        BufReader::new(File::open(dst.join("en.jsonl")).unwrap())
+            .lines()
+            .map(|line| {
+                let doc: Document = serde_json::from_str(&line.unwrap()).unwrap();
+                doc.content().clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_shuffle() {
+        let tmp = tempfile::tempdir().unwrap();
+        let (a, b, c) = (
+            tempfile::tempdir().unwrap(),
+            tempfile::tempdir().unwrap(),
+            tempfile::tempdir().unwrap(),
+        );
+        let shuffled = shuffle(42, a.path(), tmp.path());
+
+        // same documents, different order
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for content in &shuffled {
+            *counts.entry(content).or_default() += 1;
+        }
+        let expected: Vec<String> = (0..3).flat_map(docs).map(|d| d.content().clone()).collect();
+        assert_eq!(counts.len(), 300);
+        assert!(expected.iter().all(|c| counts.get(c.as_str()) == Some(&1)));
+        assert_ne!(shuffled, expected);
+
+        assert_eq!(shuffle(42, b.path(), tmp.path()), shuffled);
+        assert_ne!(shuffle(43, c.path(), tmp.path()), shuffled);
+
+        // buckets are removed
+        assert_eq!(std::fs::read_dir(tmp.path()).unwrap().count(), 0);
+    }
+}
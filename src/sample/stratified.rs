@@ -0,0 +1,173 @@
+//! Stratified sampling.
+use std::collections::BTreeMap;
+
+use crate::common::CorpusDocument;
+use crate::error::Error;
+
+use super::reservoir::Reservoir;
+use super::rng::Rng;
+
+/// Stratum of documents that have no value for the sampling criterion (no URL, no quality warning).
+pub const NO_STRATUM: &str = "none";
+
+/// Criterion used to split documents into strata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stratum {
+    /// Document-level language.
+    Language,
+    /// Host of the document URL.
+    Domain,
+    /// Quality warning. Documents with several warnings belong to several strata.
+    QualityWarning,
+}
+
+impl Stratum {
+    /// Get the strata of a document.
+    pub fn keys<D: CorpusDocument>(&self, doc: &D) -> Vec<String> {
+        let keys = match self {
+            Stratum::Language => vec![doc.identification().label().to_string()],
+            Stratum::Domain => doc.host().into_iter().collect(),
+            Stratum::QualityWarning => doc.annotations().into_iter().map(String::from).collect(),
+        };
+        if keys.is_empty() {
+            vec![NO_STRATUM.to_string()]
+        } else {
+            keys
+        }
+    }
+}
+
+/// Uniformly samples up to `per_stratum` documents in each stratum, in a single pass.
+///
+/// Each stratum has its own [Reservoir], seeded from the sampler seed and the stratum name:
+/// the sample of a stratum doesn't depend on the documents of other strata.
+#[derive(Debug, Clone)]
+pub struct StratifiedSampler<D> {
+    stratum: Stratum,
+    per_stratum: usize,
+    seed: u64,
+    reservoirs: BTreeMap<String, Reservoir<D>>,
+}
+
+impl<D: CorpusDocument + Clone> StratifiedSampler<D> {
+    pub fn new(stratum: Stratum, per_stratum: usize, seed: u64) -> Self {
+        Self {
+            stratum,
+            per_stratum,
+            seed,
+            reservoirs: BTreeMap::new(),
+        }
+    }
+
+    /// Offer a document to the reservoirs of its strata.
+    pub fn add(&mut self, doc: D) {
+        let mut keys = self.stratum.keys(&doc);
+        let last = keys.pop();
+        for key in keys {
+            self.reservoir(key).add(doc.clone());
+        }
+        if let Some(key) = last {
+            self.reservoir(key).add(doc);
+        }
+    }
+
+    fn reservoir(&mut self, key: String) -> &mut Reservoir<D> {
+        let (seed, per_stratum) = (self.seed, self.per_stratum);
+        self.reservoirs
+            .entry(key)
+            .or_insert_with_key(|key| Reservoir::with_rng(per_stratum, Rng::derive(seed, key)))
+    }
+
+    /// Offer every document of a stream (for example a file), stopping at the first error.
+    pub fn add_all<I>(&mut self, documents: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = Result<D, Error>>,
+    {
+        for doc in documents {
+            self.add(doc?);
+        }
+        Ok(())
+    }
+
+    /// Get the sampled documents of each stratum, in stream order.
+    pub fn into_samples(self) -> BTreeMap<String, Vec<D>> {
+        self.reservoirs
+            .into_iter()
+            .map(|(key, reservoir)| (key, reservoir.into_items()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StratifiedSampler, Stratum, NO_STRATUM};
+    use crate::test_utils::{doc_in, with_url, with_warnings};
+    use crate::v3::Document;
+
+    fn doc(idx: usize, lang: &str, warnings: &[&str]) -> Document {
+        let doc = doc_in(&format!("document {idx}"), lang, 1.0);
+        with_url(
+            with_warnings(doc, warnings),
+            &format!("https://site{}.org/", idx % 3),
+        )
+    }
+
+    fn docs() -> Vec<Document> {
+        (0..100)
+            .map(|i| match i % 4 {
+                0 => doc(i, "fr", &["tiny"]),
+                1 => doc(i, "fr", &["tiny", "noisy"]),
+                2 => doc(i, "en", &[]),
+                _ => doc(i, "br", &["noisy"]),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_language() {
+        let mut sampler = StratifiedSampler::new(Stratum::Language, 5, 42);
+        sampler.add_all(docs().into_iter().map(Ok)).unwrap();
+        let samples = sampler.into_samples();
+        assert_eq!(samples.keys().collect::<Vec<_>>(), vec!["br", "en", "fr"]);
+        assert!(samples.values().all(|s| s.len() == 5));
+        assert!(samples["br"]
+            .iter()
+            .all(|d| d.identification().label().as_str() == "br"));
+
+        // adding other strata doesn't change the sample of a stratum
+        let mut fr_only = StratifiedSampler::new(Stratum::Language, 5, 42);
+        fr_only
+            .add_all(
+                docs()
+                    .into_iter()
+                    .filter(|d| d.identification().label().as_str() == "fr")
+                    .map(Ok),
+            )
+            .unwrap();
+        let contents = |docs: &[Document]| -> Vec<String> {
+            docs.iter().map(|d| d.content().clone()).collect()
+        };
+        assert_eq!(
+            contents(&fr_only.into_samples()["fr"]),
+            contents(&samples["fr"])
+        );
+    }
+
+    #[test]
+    fn test_warnings_and_domains() {
+        let mut sampler = StratifiedSampler::new(Stratum::QualityWarning, 100, 0);
+        sampler.add_all(docs().into_iter().map(Ok)).unwrap();
+        let samples = sampler.into_samples();
+        assert_eq!(samples["tiny"].len(), 50);
+        assert_eq!(samples["noisy"].len(), 50);
+        assert_eq!(samples[NO_STRATUM].len(), 25);
+
+        let mut sampler = StratifiedSampler::new(Stratum::Domain, 1, 0);
+        sampler.add_all(docs().into_iter().map(Ok)).unwrap();
+        let samples = sampler.into_samples();
+        assert_eq!(
+            samples.keys().collect::<Vec<_>>(),
+            vec!["site0.org", "site1.org", "site2.org"]
+        );
+    }
+}
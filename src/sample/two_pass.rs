@@ -0,0 +1,204 @@
+//! Two-pass sampling, for corpora too large to keep a sample in memory.
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::error::Error;
+
+use super::rng::Rng;
+
+/// Uniformly samples `n` documents from several files, knowing the number of documents of each file.
+///
+/// The first pass counts documents ([TwoPassSampler::count], unless counts are already known, from a [crate::stats::Report] for example).
+/// The second pass streams sampled documents, in corpus order, to a sink (a writer, for example) without keeping them in memory.
+#[derive(Debug, Clone, Copy)]
+pub struct TwoPassSampler {
+    n: u64,
+    seed: u64,
+}
+
+impl TwoPassSampler {
+    pub fn new(n: u64, seed: u64) -> Self {
+        Self { n, seed }
+    }
+
+    /// First pass: count the documents of each file.
+    pub fn count<P, F, I, D>(paths: &[P], open: F) -> Result<Vec<u64>, Error>
+    where
+        P: AsRef<Path>,
+        F: Fn(&Path) -> Result<I, Error>,
+        I: IntoIterator<Item = Result<D, Error>>,
+    {
+        paths
+            .iter()
+            .map(|path| {
+                let mut count = 0;
+                for doc in open(path.as_ref())? {
+                    doc?;
+                    count += 1;
+                }
+                Ok(count)
+            })
+            .collect()
+    }
+
+    /// Choose the indices of sampled documents in each file, sorted.
+    pub fn indices(&self, counts: &[u64]) -> Vec<Vec<u64>> {
+        let total: u64 = counts.iter().sum();
+        let n = self.n.min(total);
+
+        // Floyd's algorithm
+        let mut rng = Rng::new(self.seed);
+        let mut chosen = HashSet::with_capacity(n as usize);
+        for j in total - n..total {
+            let t = rng.below(j + 1);
+            if !chosen.insert(t) {
+                chosen.insert(j);
+            }
+        }
+        let mut chosen: Vec<u64> = chosen.into_iter().collect();
+        chosen.sort_unstable();
+
+        let mut per_file = Vec::with_capacity(counts.len());
+        let mut chosen = chosen.into_iter().peekable();
+        let mut offset = 0;
+        for count in counts {
+            let mut indices = Vec::new();
+            while let Some(idx) = chosen.next_if(|idx| *idx < offset + count) {
+                indices.push(idx - offset);
+            }
+            per_file.push(indices);
+            offset += count;
+        }
+        per_file
+    }
+
+    /// Second pass: send sampled documents to `sink`, in corpus order.
+    ///
+    /// `counts` must be the document counts of `paths`, otherwise an error is returned.
+    /// Returns the number of sampled documents.
+    pub fn sample<P, F, I, D, S>(
+        &self,
+        paths: &[P],
+        counts: &[u64],
+        open: F,
+        mut sink: S,
+    ) -> Result<u64, Error>
+    where
+        P: AsRef<Path>,
+        F: Fn(&Path) -> Result<I, Error>,
+        I: IntoIterator<Item = Result<D, Error>>,
+        S: FnMut(D) -> Result<(), Error>,
+    {
+        if paths.len() != counts.len() {
+            return Err(Error::Custom(format!(
+                "got {} document counts for {} files",
+                counts.len(),
+                paths.len()
+            )));
+        }
+
+        let mut sampled = 0;
+        for ((path, count), indices) in paths.iter().zip(counts).zip(self.indices(counts)) {
+            if indices.is_empty() {
+                continue;
+            }
+            let mut indices = indices.into_iter().peekable();
+            let mut seen = 0;
+            for doc in open(path.as_ref())? {
+                let doc = doc?;
+                if indices.next_if_eq(&seen).is_some() {
+                    sink(doc)?;
+                    sampled += 1;
+                }
+                seen += 1;
+            }
+            if seen != *count {
+                return Err(Error::Custom(format!(
+                    "{:?} has {seen} documents, expected {count}",
+                    path.as_ref()
+                )));
+            }
+        }
+        Ok(sampled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::path::Path;
+
+    use oxilangtag::LanguageTag;
+
+    use super::TwoPassSampler;
+    use crate::error::Error;
+    use crate::oscar_doc;
+    use crate::test_utils::doc;
+    use crate::v3::{self, Document, WriterTrait};
+
+    fn open(path: &Path) -> Result<oscar_doc::Reader<BufReader<File>>, Error> {
+        Ok(oscar_doc::Reader::new(BufReader::new(File::open(path)?)))
+    }
+
+    #[test]
+    fn test_indices() {
+        let sampler = TwoPassSampler::new(10, 42);
+        let indices = sampler.indices(&[5, 0, 100, 3]);
+        assert_eq!(indices.len(), 4);
+        assert_eq!(indices.iter().map(Vec::len).sum::<usize>(), 10);
+        assert!(indices[1].is_empty());
+        assert!(indices[2].iter().all(|i| *i < 100));
+        assert!(indices[3].iter().all(|i| *i < 3));
+        assert_eq!(sampler.indices(&[5, 0, 100, 3]), indices);
+
+        let all = TwoPassSampler::new(10, 42).indices(&[2, 3]);
+        assert_eq!(all, vec![vec![0, 1], vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn test_sample() {
+        let paths = ["tests/res/data.jsonl"; 2];
+        let counts = TwoPassSampler::count(&paths, open).unwrap();
+        let per_file = counts[0];
+        assert_eq!(counts, vec![per_file; 2]);
+
+        let mut sampled = Vec::new();
+        let n = TwoPassSampler::new(20, 1)
+            .sample(&paths, &counts, open, |doc| {
+                sampled.push(doc);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(n, 20);
+        assert_eq!(sampled.len(), 20);
+
+        assert!(TwoPassSampler::new(20, 1)
+            .sample(&paths, &[per_file + 1, per_file], open, |_| Ok(()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_write() {
+        let docs: Vec<Document> = (0..25).map(|i| doc(format!("doc {i}"))).collect();
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = v3::Writer::new(
+            dir.path(),
+            LanguageTag::parse("en".to_string()).unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+        TwoPassSampler::new(5, 0)
+            .sample(
+                &["a", "b"],
+                &[25, 25],
+                |_| Ok(docs.iter().cloned().map(Ok)),
+                |doc| writer.write(vec![doc]),
+            )
+            .unwrap();
+        writer.flush().unwrap();
+        let written = std::fs::read_to_string(dir.path().join("en.jsonl")).unwrap();
+        assert_eq!(written.lines().count(), 5);
+    }
+}
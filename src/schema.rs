@@ -0,0 +1,231 @@
+/*! JSON Schemas of document formats
+
+Schemas are generated from the document types themselves, so they stay in sync with what readers and writers expect.
+They can be emitted with [Format::to_json] (or `Document::get_schema()`), and used to check arbitrary JSONL files with a [Validator]:
+
+```
+use oscar_io::schema::{Format, Validator};
+
+let validator = Validator::new(Format::V3).unwrap();
+let violations = validator.validate_jsonl("{\"content\": 1}\n".as_bytes()).unwrap();
+assert!(!violations.is_empty());
+assert_eq!(violations[0].line(), 1);
+```
+!*/
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use jsonschema::JSONSchema;
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+use serde_json::Value;
+
+use crate::error::Error;
+use crate::{oscar_doc, v3};
+
+/// Document formats that have a schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// OSCAR 22.01 documents ([oscar_doc::Document]).
+    OscarDoc,
+    /// OSCAR 23.01 documents ([v3::Document]).
+    V3,
+}
+
+impl Format {
+    /// Get the format's schema.
+    pub fn schema(&self) -> RootSchema {
+        match self {
+            Self::OscarDoc => schema_for!(oscar_doc::Document),
+            Self::V3 => schema_for!(v3::Document),
+        }
+    }
+
+    /// Get the format's schema as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(&self.schema())?)
+    }
+}
+
+/// A schema violation found on a given line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    line: usize,
+    path: String,
+    message: String,
+}
+
+impl Violation {
+    /// Get the line number (starting at 1).
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Get the JSON pointer to the faulty value (empty for the whole line).
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Get a reference to the violation message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "line {}: {}", self.line, self.message)
+        } else {
+            write!(f, "line {}: {}: {}", self.line, self.path, self.message)
+        }
+    }
+}
+
+/// Validates JSON documents against a compiled schema.
+pub struct Validator {
+    schema: JSONSchema,
+}
+
+impl Validator {
+    /// Create a validator for one of the document formats.
+    pub fn new(format: Format) -> Result<Self, Error> {
+        Self::from_schema(&serde_json::to_value(format.schema())?)
+    }
+
+    /// Create a validator from an arbitrary JSON Schema.
+    pub fn from_schema(schema: &Value) -> Result<Self, Error> {
+        let schema = JSONSchema::compile(schema)
+            .map_err(|e| Error::Custom(format!("invalid schema: {e}")))?;
+        Ok(Self { schema })
+    }
+
+    /// Check a single line. Lines that are not valid JSON yield a single violation.
+    pub fn validate_line(&self, line_number: usize, line: &str) -> Vec<Violation> {
+        let value: Value = match serde_json::from_str(line) {
+            Ok(v) => v,
+            Err(e) => {
+                return vec![Violation {
+                    line: line_number,
+                    path: String::new(),
+                    message: format!("invalid JSON: {e}"),
+                }]
+            }
+        };
+
+        let violations: Vec<Violation> = match self.schema.validate(&value) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors
+                .map(|e| Violation {
+                    line: line_number,
+                    path: e.instance_path.to_string(),
+                    message: e.to_string(),
+                })
+                .collect(),
+        };
+        violations
+    }
+
+    /// Check every line of a JSONL stream. Blank lines are skipped.
+    ///
+    /// Returns every violation found, or an error if the stream can't be read.
+    pub fn validate_jsonl<R: BufRead>(&self, r: R) -> Result<Vec<Violation>, Error> {
+        let mut violations = Vec::new();
+        for (idx, line) in r.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            violations.extend(self.validate_line(idx + 1, &line));
+        }
+        Ok(violations)
+    }
+
+    /// Check every line of a JSONL file.
+    pub fn validate_path(&self, path: &Path) -> Result<Vec<Violation>, Error> {
+        self.validate_jsonl(BufReader::new(File::open(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use oxilangtag::LanguageTag;
+    use warc::Record;
+
+    use super::{Format, Validator};
+    use crate::common::Identification;
+    use crate::v3;
+
+    #[test]
+    fn test_oscar_doc_valid() {
+        let validator = Validator::new(Format::OscarDoc).unwrap();
+        let violations = validator
+            .validate_path(Path::new("tests/res/data.jsonl"))
+            .unwrap();
+        assert!(violations.is_empty(), "{violations:?}");
+    }
+
+    #[test]
+    fn test_v3_valid() {
+        let id = Identification::new(LanguageTag::parse("fr".to_string()).unwrap(), 0.9);
+        let mut metadata = v3::Metadata::new(&id, &[Some(id.clone()), None]);
+        metadata.set_extension("toxicity", &0.1).unwrap();
+        let record = Record::default().add_body("foo\nbar");
+        let doc = v3::Document::from_record(record, metadata);
+
+        let validator = Validator::new(Format::V3).unwrap();
+        let line = serde_json::to_string(&doc).unwrap();
+        assert!(validator.validate_line(1, &line).is_empty());
+    }
+
+    #[test]
+    fn test_violations() {
+        let validator = Validator::new(Format::V3).unwrap();
+        let data = r#"{"content":"foo","warc_headers":{},"metadata":{"identification":{"label":"en","prob":-0.5},"harmful_pp":null,"tlsh":null,"quality_warnings":null,"categories":null,"sentence_identifications":[null]}}
+
+not json
+{"content":"foo","warc_headers":{"warc-type":1}}
+"#;
+        let violations = validator.validate_jsonl(data.as_bytes()).unwrap();
+        let lines: Vec<usize> = violations.iter().map(|v| v.line()).collect();
+        assert_eq!(lines, vec![1, 3, 4, 4]);
+        assert_eq!(violations[0].path(), "/metadata/identification/prob");
+        assert!(violations[1].message().starts_with("invalid JSON"));
+        let paths: Vec<&str> = violations[2..].iter().map(|v| v.path()).collect();
+        assert!(paths.contains(&"/warc_headers/warc-type"));
+        assert!(paths.contains(&""));
+    }
+
+    #[test]
+    fn test_prob_bounds() {
+        // the schema and Metadata::validate accept the same probabilities
+        let validator = Validator::new(Format::V3).unwrap();
+        for (prob, valid) in [(0.0, true), (1.0005, true), (1.01, false), (-0.0005, false)] {
+            let id = Identification::new(LanguageTag::parse("fr".to_string()).unwrap(), prob);
+            let metadata = v3::Metadata::new(&id, &[Some(id.clone())]);
+            let doc = v3::Document::from_record(Record::default().add_body("foo"), metadata);
+            let line = serde_json::to_string(&doc).unwrap();
+            assert_eq!(
+                validator.validate_line(1, &line).is_empty(),
+                valid,
+                "{prob}"
+            );
+            assert_eq!(doc.metadata().validate().is_ok(), valid, "{prob}");
+        }
+    }
+
+    #[test]
+    fn test_get_schema() {
+        let schema: serde_json::Value =
+            serde_json::from_str(&v3::Document::get_schema().unwrap()).unwrap();
+        assert_eq!(schema["title"], "Document");
+        assert_eq!(
+            schema["definitions"]["Identification"]["properties"]["label"]["type"],
+            "string"
+        );
+    }
+}
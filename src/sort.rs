@@ -0,0 +1,322 @@
+/*! External merge sort
+
+[ExternalSort] sorts documents that don't fit in memory by a key extracted with a closure
+(URL, record id, date, content hash...):
+
+1. documents are read in runs of `run_size` documents, each run is sorted in memory and spilled to a temporary file
+   (optionally compressed, see [ExternalSort::with_comp]),
+2. runs are merged into a writer (a rotating [crate::v3::Writer], for example), at most `fan_in` at once
+   (see [ExternalSort::with_fan_in]): when there are more runs, they are first merged into bigger temporary runs.
+
+The sort is stable: documents with equal keys keep their input order.
+
+```
+use oscar_io::dedup::hash;
+use oscar_io::sort::ExternalSort;
+use oscar_io::v3::Document;
+
+// by URL, documents without URL first
+let by_url = ExternalSort::new(|d: &Document| d.url(), 100_000);
+// by content hash
+let by_hash = ExternalSort::new(|d: &Document| hash(d.content().as_bytes()), 100_000);
+```
+!*/
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::common::tmp::TmpDir;
+use crate::error::Error;
+use crate::v3::{Comp, NewWriter, WriterTrait};
+
+/// Default maximum number of runs merged at once.
+pub const DEFAULT_FAN_IN: usize = 64;
+
+/// Sorts documents by key, with at most `run_size` documents in memory.
+#[derive(Debug, Clone)]
+pub struct ExternalSort<F> {
+    key: F,
+    run_size: usize,
+    fan_in: usize,
+    tmp_dir: Option<PathBuf>,
+    comp: Option<Comp>,
+}
+
+impl<F> ExternalSort<F> {
+    pub fn new(key: F, run_size: usize) -> Self {
+        Self {
+            key,
+            run_size: run_size.max(1),
+            fan_in: DEFAULT_FAN_IN,
+            tmp_dir: None,
+            comp: None,
+        }
+    }
+
+    /// Maximum number of runs merged at once, which is also the number of files opened at once (at least 2).
+    /// Defaults to [DEFAULT_FAN_IN].
+    pub fn with_fan_in(mut self, fan_in: usize) -> Self {
+        self.fan_in = fan_in.max(2);
+        self
+    }
+
+    /// Directory in which temporary runs are created. Defaults to [std::env::temp_dir].
+    pub fn with_tmp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.tmp_dir = Some(dir.into());
+        self
+    }
+
+    /// Compress temporary runs.
+    pub fn with_comp(mut self, comp: Comp) -> Self {
+        self.comp = Some(comp);
+        self
+    }
+
+    /// Sort `documents` into `writer`.
+    ///
+    /// Temporary runs are removed afterwards, even on errors. The writer is not flushed.
+    /// Returns the number of documents written.
+    pub fn sort<I, D, K, W>(&self, documents: I, writer: &mut W) -> Result<u64, Error>
+    where
+        I: IntoIterator<Item = Result<D, Error>>,
+        D: Serialize + DeserializeOwned,
+        F: Fn(&D) -> K,
+        K: Ord,
+        W: WriterTrait<Item = D>,
+    {
+        let mut tmp = None;
+        let mut runs = Vec::new();
+        let mut run = Vec::with_capacity(self.run_size);
+        let mut documents = documents.into_iter().peekable();
+
+        while let Some(doc) = documents.next() {
+            let doc = doc?;
+            run.push(((self.key)(&doc), doc));
+            if run.len() < self.run_size {
+                continue;
+            }
+            // everything fits in memory
+            if runs.is_empty() && documents.peek().is_none() {
+                break;
+            }
+            let dir = match &tmp {
+                Some(dir) => dir,
+                None => tmp.insert(TmpDir::create(self.tmp_dir.as_deref(), "sort")?),
+            };
+            runs.push(self.spill(dir.path(), runs.len(), &mut run)?);
+        }
+
+        if runs.is_empty() {
+            run.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let written = run.len() as u64;
+            writer.write(run.into_iter().map(|(_, doc)| doc).collect())?;
+            return Ok(written);
+        }
+        let dir = tmp
+            .as_ref()
+            .expect("runs are spilled in a temporary directory")
+            .path();
+        if !run.is_empty() {
+            runs.push(self.spill(dir, runs.len(), &mut run)?);
+        }
+
+        // merge consecutive runs, so that the sort stays stable
+        let mut idx = runs.len();
+        while runs.len() > self.fan_in {
+            let mut merged = Vec::with_capacity(runs.len().div_ceil(self.fan_in));
+            for group in runs.chunks(self.fan_in) {
+                merged.push(self.merge_runs(dir, idx, group)?);
+                idx += 1;
+            }
+            runs = merged;
+        }
+        self.merge(&runs, |doc| writer.write(vec![doc]))
+    }
+
+    /// Sort `run` and write it in a temporary file, leaving it empty.
+    fn spill<D, K>(&self, dir: &Path, idx: usize, run: &mut Vec<(K, D)>) -> Result<PathBuf, Error>
+    where
+        D: Serialize,
+        K: Ord,
+    {
+        run.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut file = NewWriter::new(dir, format!("run-{idx}"), self.comp.clone(), None)?;
+        for (_, doc) in run.drain(..) {
+            serde_json::to_writer(&mut file, &doc)?;
+            file.write_all(b"\n")?;
+        }
+        file.flush()?;
+        Ok(file.current_filepath())
+    }
+
+    /// Merge `runs` into a new temporary run, removing them.
+    fn merge_runs<D, K>(&self, dir: &Path, idx: usize, runs: &[PathBuf]) -> Result<PathBuf, Error>
+    where
+        D: Serialize + DeserializeOwned,
+        F: Fn(&D) -> K,
+        K: Ord,
+    {
+        let mut file = NewWriter::new(dir, format!("run-{idx}"), self.comp.clone(), None)?;
+        self.merge(runs, |doc: D| {
+            serde_json::to_writer(&mut file, &doc)?;
+            file.write_all(b"\n")?;
+            Ok(())
+        })?;
+        file.flush()?;
+        for run in runs {
+            std::fs::remove_file(run)?;
+        }
+        Ok(file.current_filepath())
+    }
+
+    /// k-way merge of sorted runs. Ties are broken by run index, which keeps the sort stable.
+    fn merge<D, K>(
+        &self,
+        runs: &[PathBuf],
+        mut emit: impl FnMut(D) -> Result<(), Error>,
+    ) -> Result<u64, Error>
+    where
+        D: DeserializeOwned,
+        F: Fn(&D) -> K,
+        K: Ord,
+    {
+        let mut readers = runs
+            .iter()
+            .map(|path| self.open_run(path))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut heads: Vec<Option<D>> = Vec::with_capacity(runs.len());
+        let mut heap = BinaryHeap::with_capacity(runs.len());
+
+        let mut next = |idx: usize, heads: &mut Vec<Option<D>>| -> Result<Option<K>, Error> {
+            let doc = match readers[idx].next() {
+                Some(line) => serde_json::from_str::<D>(&line?)?,
+                None => return Ok(None),
+            };
+            let key = (self.key)(&doc);
+            heads[idx] = Some(doc);
+            Ok(Some(key))
+        };
+
+        for idx in 0..runs.len() {
+            heads.push(None);
+            if let Some(key) = next(idx, &mut heads)? {
+                heap.push(Reverse((key, idx)));
+            }
+        }
+
+        let mut written = 0;
+        while let Some(Reverse((_, idx))) = heap.pop() {
+            let doc = heads[idx].take().expect("runs in the heap have a document");
+            emit(doc)?;
+            written += 1;
+            if let Some(key) = next(idx, &mut heads)? {
+                heap.push(Reverse((key, idx)));
+            }
+        }
+        Ok(written)
+    }
+
+    fn open_run(&self, path: &Path) -> Result<std::io::Lines<Box<dyn BufRead>>, Error> {
+        let file = File::open(path)?;
+        let reader: Box<dyn BufRead> = match self.comp {
+            None => Box::new(BufReader::new(file)),
+            Some(Comp::Zstd { .. }) => Box::new(BufReader::new(zstd::Decoder::new(file)?)),
+        };
+        Ok(reader.lines())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+    use std::path::Path;
+
+    use oxilangtag::LanguageTag;
+
+    use super::ExternalSort;
+    use crate::test_utils::{doc, with_url};
+    use crate::v3::{self, Comp, Document, WriterTrait};
+
+    fn docs() -> Vec<Document> {
+        (0..100)
+            .map(|i| {
+                // 10 documents per URL, inserted in a scrambled order
+                let url = format!("https://example.com/{}", (i * 7) % 10);
+                with_url(doc(format!("doc {i}")), &url)
+            })
+            .collect()
+    }
+
+    fn sorted<F>(sort: &ExternalSort<F>, dst: &Path) -> Vec<(String, String)>
+    where
+        F: Fn(&Document) -> Option<String>,
+    {
+        let mut writer = v3::Writer::new(
+            dst,
+            LanguageTag::parse("en".to_string()).unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+        let n = sort.sort(docs().into_iter().map(Ok), &mut writer).unwrap();
+        assert_eq!(n, 100);
+        writer.flush().unwrap();
+
+        BufReader::new(File::open(dst.join("en.jsonl")).unwrap())
+            .lines()
+            .map(|line| {
+                let doc: Document = serde_json::from_str(&line.unwrap()).unwrap();
+                (doc.url().unwrap(), doc.content().clone())
+            })
+            .collect()
+    }
+
+    fn by_url(d: &Document) -> Option<String> {
+        d.url()
+    }
+
+    #[test]
+    fn test_sort() {
+        // stable sort in memory
+        let mut expected: Vec<(String, String)> = docs()
+            .iter()
+            .map(|d| (by_url(d).unwrap(), d.content().clone()))
+            .collect();
+        expected.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let tmp = tempfile::tempdir().unwrap();
+        for (run_size, fan_in, comp) in [
+            (1000, 64, None),
+            (100, 64, None),
+            (7, 64, None),
+            (1, 64, None),
+            (13, 64, Some(Comp::Zstd { level: 1 })),
+            // several merge passes
+            (1, 2, None),
+            (3, 5, None),
+            (7, 2, Some(Comp::Zstd { level: 1 })),
+        ] {
+            let mut sort = ExternalSort::new(by_url, run_size)
+                .with_fan_in(fan_in)
+                .with_tmp_dir(tmp.path());
+            if let Some(comp) = comp {
+                sort = sort.with_comp(comp);
+            }
+            let dst = tempfile::tempdir().unwrap();
+            assert_eq!(
+                sorted(&sort, dst.path()),
+                expected,
+                "run size {run_size}, fan in {fan_in}"
+            );
+        }
+
+        // runs are removed
+        assert_eq!(std::fs::read_dir(tmp.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,285 @@
+/*! Deterministic train/validation/test splitting
+
+A [Splitter] assigns each document to a named split by hashing a [SplitKey] with a seed.
+Assignments only depend on the key, the seed and the split ratios: a document always lands in the same split,
+across runs and corpus versions, and documents sharing a key (for example, pages of the same site) always land together.
+
+```
+use oscar_io::split::{SplitKey, Splitter};
+
+let splitter = Splitter::new(SplitKey::RegisteredDomain, 42)
+    .with_split("train", 0.8)
+    .with_split("validation", 0.1)
+    .with_split("test", 0.1);
+assert_eq!(splitter.assign_key("bbc.co.uk"), splitter.assign_key("bbc.co.uk"));
+```
+!*/
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use oxilangtag::LanguageTag;
+
+use crate::common::CorpusDocument;
+use crate::dedup::hash;
+use crate::error::Error;
+use crate::v3::{self, Comp, Document, WriterTrait};
+
+/// Second-level labels under which country code top-level domains register domains (as in `bbc.co.uk`).
+const GENERIC_SLDS: &[&str] = &[
+    "ac", "co", "com", "edu", "gob", "gov", "ne", "net", "or", "org",
+];
+
+/// Get the registered domain of a host (ex. `news.bbc.co.uk` → `bbc.co.uk`), lowercased.
+///
+/// This is a heuristic, not a lookup in the public suffix list:
+/// the last two labels are kept, or three if the host is under a two-letter top-level domain
+/// and a generic second-level label (`co`, `com`, `org`...). IP addresses are kept as is.
+pub fn registered_domain(host: &str) -> String {
+    let host = host.trim_end_matches('.').to_lowercase();
+    if host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[') {
+        return host;
+    }
+    let labels: Vec<&str> = host.split('.').collect();
+    let keep = match labels.as_slice() {
+        [.., sld, tld] if tld.len() == 2 && GENERIC_SLDS.contains(sld) => 3,
+        _ => 2,
+    };
+    labels[labels.len().saturating_sub(keep)..].join(".")
+}
+
+/// Document key used to assign splits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitKey {
+    /// WARC record id: documents are split independently.
+    RecordId,
+    /// Document URL: copies of a page land in the same split.
+    Url,
+    /// Registered domain of the URL (see [registered_domain]): pages of a site land in the same split.
+    RegisteredDomain,
+}
+
+impl SplitKey {
+    /// Get the key of a document, if it has one.
+    pub fn key<D: CorpusDocument>(&self, doc: &D) -> Option<String> {
+        match self {
+            SplitKey::RecordId => doc.record_id().map(|id| id.into_owned()),
+            SplitKey::Url => doc.url().map(|url| url.into_owned()),
+            SplitKey::RegisteredDomain => doc.host().map(|host| registered_domain(&host)),
+        }
+    }
+}
+
+/// Assigns documents to named splits.
+///
+/// Documents without key (no URL, for example) are assigned by hashing their content.
+#[derive(Debug, Clone)]
+pub struct Splitter {
+    key: SplitKey,
+    seed: u64,
+    splits: Vec<(String, f64)>,
+}
+
+impl Splitter {
+    /// Splitter without splits, see [Splitter::with_split].
+    pub fn new(key: SplitKey, seed: u64) -> Self {
+        Self {
+            key,
+            seed,
+            splits: Vec::new(),
+        }
+    }
+
+    /// Add a split. Ratios are relative to the sum of all ratios.
+    ///
+    /// Changing ratios or the order of splits changes assignments.
+    pub fn with_split(mut self, name: impl Into<String>, ratio: f64) -> Self {
+        self.splits.push((name.into(), ratio.max(0.0)));
+        self
+    }
+
+    /// Get split names, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.splits.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Assign a key to a split. Returns [None] if there are no splits with a positive ratio.
+    pub fn assign_key(&self, key: &str) -> Option<&str> {
+        let total: f64 = self.splits.iter().map(|(_, ratio)| ratio).sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut bytes = self.seed.to_le_bytes().to_vec();
+        bytes.extend_from_slice(key.as_bytes());
+        // 53 bits, so that the position is exactly representable
+        let position = (hash(&bytes) >> 11) as f64 / (1u64 << 53) as f64 * total;
+
+        let mut upper = 0.0;
+        for (name, ratio) in &self.splits {
+            upper += ratio;
+            if position < upper {
+                return Some(name);
+            }
+        }
+        // rounding errors
+        self.splits
+            .iter()
+            .rev()
+            .find(|(_, ratio)| *ratio > 0.0)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Assign a document to a split.
+    pub fn assign<D: CorpusDocument>(&self, doc: &D) -> Option<&str> {
+        match self.key.key(doc) {
+            Some(key) => self.assign_key(&key),
+            None => self.assign_key(doc.content()),
+        }
+    }
+
+    /// Write each document into the v3 writer of its split, at `dst/<split>/<lang>.jsonl`.
+    ///
+    /// Returns the number of documents written in each split.
+    pub fn write<I>(
+        &self,
+        documents: I,
+        dst: &Path,
+        lang: LanguageTag<String>,
+        comp: Option<Comp>,
+    ) -> Result<BTreeMap<String, u64>, Error>
+    where
+        I: IntoIterator<Item = Result<Document, Error>>,
+    {
+        let mut writers = BTreeMap::new();
+        for name in self.names() {
+            let dir = dst.join(name);
+            std::fs::create_dir_all(&dir)?;
+            let writer = v3::Writer::new(&dir, lang.clone(), None, comp.clone())?;
+            writers.insert(name.to_string(), (writer, 0));
+        }
+
+        for doc in documents {
+            let doc = doc?;
+            if let Some(split) = self.assign(&doc) {
+                let (writer, count) = writers.get_mut(split).expect("splits have a writer");
+                writer.write(vec![doc])?;
+                *count += 1;
+            }
+        }
+
+        writers
+            .into_iter()
+            .map(|(name, (mut writer, count))| {
+                writer.flush()?;
+                Ok((name, count))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use oxilangtag::LanguageTag;
+
+    use super::{registered_domain, SplitKey, Splitter};
+    use crate::test_utils::{self, with_url};
+    use crate::v3::Document;
+
+    fn doc(url: &str) -> Document {
+        with_url(test_utils::doc(format!("page {url}")), url)
+    }
+
+    fn splitter(key: SplitKey) -> Splitter {
+        Splitter::new(key, 7)
+            .with_split("train", 0.8)
+            .with_split("validation", 0.1)
+            .with_split("test", 0.1)
+    }
+
+    #[test]
+    fn test_registered_domain() {
+        assert_eq!(registered_domain("news.bbc.co.uk"), "bbc.co.uk");
+        assert_eq!(registered_domain("www.Example.com."), "example.com");
+        assert_eq!(registered_domain("fr.wikipedia.org"), "wikipedia.org");
+        assert_eq!(registered_domain("a.b.abc.net.au"), "abc.net.au");
+        assert_eq!(registered_domain("localhost"), "localhost");
+        assert_eq!(registered_domain("192.168.0.1"), "192.168.0.1");
+    }
+
+    #[test]
+    fn test_assign() {
+        let s = splitter(SplitKey::RegisteredDomain);
+        let a = doc("https://fr.wikipedia.org/wiki/A");
+        let b = doc("https://en.wikipedia.org/wiki/B");
+        assert_eq!(s.assign(&a), s.assign(&b));
+
+        // stable across runs: never change this without a good reason
+        let names: Vec<&str> = (0..16)
+            .map(|i| s.assign_key(&format!("site{i}.com")).unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["train"; 15]
+                .into_iter()
+                .chain(["test"])
+                .collect::<Vec<_>>()
+        );
+
+        // ratios are respected
+        let mut counts = std::collections::HashMap::new();
+        for i in 0..10000 {
+            *counts
+                .entry(s.assign_key(&i.to_string()).unwrap())
+                .or_insert(0) += 1;
+        }
+        assert!((7800..8200).contains(&counts["train"]), "{counts:?}");
+        assert!((850..1150).contains(&counts["test"]), "{counts:?}");
+
+        // other seeds give other assignments
+        let other = Splitter::new(SplitKey::Url, 8)
+            .with_split("train", 0.8)
+            .with_split("validation", 0.1)
+            .with_split("test", 0.1);
+        assert!((0..100).any(|i| {
+            let key = i.to_string();
+            other.assign_key(&key) != s.assign_key(&key)
+        }));
+
+        assert_eq!(Splitter::new(SplitKey::Url, 0).assign(&a), None);
+        assert_eq!(
+            Splitter::new(SplitKey::Url, 0)
+                .with_split("empty", 0.0)
+                .with_split("all", 1.0)
+                .assign(&a),
+            Some("all")
+        );
+    }
+
+    #[test]
+    fn test_write() {
+        let docs: Vec<Document> = (0..100)
+            .map(|i| doc(&format!("https://site{}.com/{i}", i % 20)))
+            .collect();
+        let s = splitter(SplitKey::RegisteredDomain);
+        let dir = tempfile::tempdir().unwrap();
+        let counts = s
+            .write(
+                docs.clone().into_iter().map(Ok),
+                dir.path(),
+                LanguageTag::parse("en".to_string()).unwrap(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(counts.values().sum::<u64>(), 100);
+
+        for (split, count) in counts {
+            let written = std::fs::read_to_string(dir.path().join(&split).join("en.jsonl"))
+                .unwrap_or_default();
+            assert_eq!(written.lines().count() as u64, count);
+            for line in written.lines() {
+                let doc: Document = serde_json::from_str(line).unwrap();
+                assert_eq!(s.assign(&doc), Some(split.as_str()));
+            }
+        }
+    }
+}
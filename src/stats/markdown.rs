@@ -0,0 +1,91 @@
+//! Markdown rendering of reports, for release notes.
+use std::fmt::Write;
+
+use super::report::{Histogram, Report, Stats};
+
+fn table(out: &mut String, header: &[&str], rows: Vec<Vec<String>>) {
+    writeln!(out, "| {} |", header.join(" | ")).unwrap();
+    let separators: Vec<&str> = header.iter().map(|_| "---").collect();
+    writeln!(out, "| {} |", separators.join(" | ")).unwrap();
+    for row in rows {
+        writeln!(out, "| {} |", row.join(" | ")).unwrap();
+    }
+    out.push('\n');
+}
+
+fn overview_row(name: &str, stats: &Stats) -> Vec<String> {
+    vec![
+        name.to_string(),
+        stats.documents.to_string(),
+        stats.bytes.to_string(),
+        stats.lines.to_string(),
+        stats.words.to_string(),
+        format!("{:.3}", stats.avg_prob()),
+    ]
+}
+
+fn counts_rows<'a>(counts: impl Iterator<Item = (&'a str, u64)>) -> Vec<Vec<String>> {
+    counts
+        .map(|(key, count)| vec![key.to_string(), count.to_string()])
+        .collect()
+}
+
+impl Report {
+    /// Render the report as Markdown tables: an overview per language,
+    /// then quality warnings, categories, harmful perplexity and the `top_domains` most frequent domains for the whole corpus.
+    pub fn to_markdown(&self, top_domains: usize) -> String {
+        let total = self.total();
+        let mut out = String::new();
+
+        out.push_str("## Languages\n\n");
+        let mut rows: Vec<Vec<String>> = self
+            .languages
+            .iter()
+            .map(|(lang, stats)| overview_row(lang, stats))
+            .collect();
+        rows.push(overview_row("**total**", &total));
+        table(
+            &mut out,
+            &[
+                "Language",
+                "Documents",
+                "Bytes",
+                "Lines",
+                "Words",
+                "Avg. prob",
+            ],
+            rows,
+        );
+
+        out.push_str("## Quality warnings\n\n");
+        let warnings = total.quality_warnings.iter().map(|(k, v)| (k.as_str(), *v));
+        table(&mut out, &["Warning", "Documents"], counts_rows(warnings));
+
+        out.push_str("## Categories\n\n");
+        let categories = total.categories.iter().map(|(k, v)| (k.as_str(), *v));
+        table(
+            &mut out,
+            &["Category", "Documents"],
+            counts_rows(categories),
+        );
+
+        out.push_str("## Harmful perplexity\n\n");
+        let labels = Histogram::labels();
+        let bins = labels
+            .iter()
+            .map(String::as_str)
+            .zip(total.harmful_pp.bins().iter().copied())
+            .chain(std::iter::once(("missing", total.harmful_pp.missing())));
+        table(
+            &mut out,
+            &["Harmful perplexity", "Documents"],
+            counts_rows(bins),
+        );
+
+        out.push_str("## Domains\n\n");
+        let domains = total.top_domains(top_domains).into_iter();
+        table(&mut out, &["Domain", "Documents"], counts_rows(domains));
+
+        out
+    }
+}
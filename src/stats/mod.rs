@@ -0,0 +1,26 @@
+/*! Corpus statistics
+
+A [Report] holds per-language [Stats] (document, byte, line and word counts, average identification probability,
+quality warning, category and domain frequencies, harmful perplexity histogram).
+
+Reports are computed over any document stream ([Report::from_documents]), optionally in parallel across files ([Report::from_files]),
+and can be serialized to JSON ([Report::to_json]), rendered as Markdown ([Report::to_markdown]) and merged ([Report::merge]).
+
+```
+use std::fs::File;
+use std::io::BufReader;
+
+use oscar_io::oscar_doc;
+use oscar_io::stats::Report;
+
+let report = Report::from_files(&["tests/res/data.jsonl"], 4, |path| {
+    Ok(oscar_doc::Reader::new(BufReader::new(File::open(path)?)))
+})
+.unwrap();
+println!("{}", report.to_markdown(10));
+```
+!*/
+mod markdown;
+mod report;
+
+pub use report::{Histogram, Report, Stats, HARMFUL_PP_BOUNDS};
@@ -0,0 +1,361 @@
+//! Statistics reports and their merging.
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::CorpusDocument;
+use crate::error::Error;
+
+/// Upper bounds (exclusive) of the harmful perplexity histogram bins.
+/// The last bin holds values at or above the last bound.
+pub const HARMFUL_PP_BOUNDS: [f32; 10] = [
+    10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+];
+
+/// Harmful perplexity histogram, with bins bounded by [HARMFUL_PP_BOUNDS].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Histogram {
+    bins: Vec<u64>,
+    /// Documents without harmful perplexity.
+    missing: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            bins: vec![0; HARMFUL_PP_BOUNDS.len() + 1],
+            missing: 0,
+        }
+    }
+}
+
+impl Histogram {
+    fn add(&mut self, value: Option<f32>) {
+        match value {
+            Some(value) => {
+                let bin = HARMFUL_PP_BOUNDS
+                    .iter()
+                    .position(|bound| value < *bound)
+                    .unwrap_or(HARMFUL_PP_BOUNDS.len());
+                self.bins[bin] += 1;
+            }
+            None => self.missing += 1,
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        for (bin, count) in self.bins.iter_mut().zip(other.bins.iter()) {
+            *bin += count;
+        }
+        self.missing += other.missing;
+    }
+
+    /// Get the document count of each bin.
+    pub fn bins(&self) -> &[u64] {
+        &self.bins
+    }
+
+    /// Get the number of documents without harmful perplexity.
+    pub fn missing(&self) -> u64 {
+        self.missing
+    }
+
+    /// Get bin labels (ex. `[10, 25)`).
+    pub fn labels() -> Vec<String> {
+        let mut lower = 0.0;
+        let mut labels: Vec<String> = HARMFUL_PP_BOUNDS
+            .iter()
+            .map(|upper| {
+                let label = format!("[{lower}, {upper})");
+                lower = *upper;
+                label
+            })
+            .collect();
+        labels.push(format!("[{lower}, +inf)"));
+        labels
+    }
+}
+
+fn merge_counts(counts: &mut BTreeMap<String, u64>, other: &BTreeMap<String, u64>) {
+    for (key, count) in other {
+        *counts.entry(key.clone()).or_default() += count;
+    }
+}
+
+/// Statistics of a set of documents.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub documents: u64,
+    /// Content size, in bytes.
+    pub bytes: u64,
+    pub lines: u64,
+    /// Whitespace-separated words.
+    pub words: u64,
+    /// Sum of identification probabilities, see [Stats::avg_prob].
+    pub prob_sum: f64,
+    pub quality_warnings: BTreeMap<String, u64>,
+    pub categories: BTreeMap<String, u64>,
+    pub harmful_pp: Histogram,
+    /// Document count per URL host.
+    pub domains: BTreeMap<String, u64>,
+}
+
+impl Stats {
+    /// Account for a document.
+    pub fn add<D: CorpusDocument>(&mut self, doc: &D) {
+        let content = doc.content();
+        self.documents += 1;
+        self.bytes += content.len() as u64;
+        self.lines += content.lines().count() as u64;
+        self.words += content.split_whitespace().count() as u64;
+        self.prob_sum += *doc.identification().prob() as f64;
+        for warning in doc.annotations() {
+            *self
+                .quality_warnings
+                .entry(warning.to_string())
+                .or_default() += 1;
+        }
+        for category in doc.categories() {
+            *self.categories.entry(category.to_string()).or_default() += 1;
+        }
+        self.harmful_pp.add(doc.harmful_pp());
+        if let Some(host) = doc.host() {
+            *self.domains.entry(host).or_default() += 1;
+        }
+    }
+
+    /// Add the statistics of other documents.
+    pub fn merge(&mut self, other: &Self) {
+        self.documents += other.documents;
+        self.bytes += other.bytes;
+        self.lines += other.lines;
+        self.words += other.words;
+        self.prob_sum += other.prob_sum;
+        merge_counts(&mut self.quality_warnings, &other.quality_warnings);
+        merge_counts(&mut self.categories, &other.categories);
+        self.harmful_pp.merge(&other.harmful_pp);
+        merge_counts(&mut self.domains, &other.domains);
+    }
+
+    /// Average identification probability (0 if there are no documents).
+    pub fn avg_prob(&self) -> f64 {
+        if self.documents == 0 {
+            0.0
+        } else {
+            self.prob_sum / self.documents as f64
+        }
+    }
+
+    /// Get the `n` most frequent domains, most frequent first.
+    pub fn top_domains(&self, n: usize) -> Vec<(&str, u64)> {
+        let mut domains: Vec<(&str, u64)> = self
+            .domains
+            .iter()
+            .map(|(domain, count)| (domain.as_str(), *count))
+            .collect();
+        domains.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        domains.truncate(n);
+        domains
+    }
+}
+
+/// Corpus statistics, per language.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub languages: BTreeMap<String, Stats>,
+}
+
+impl Report {
+    /// Account for a document, under its document-level language.
+    pub fn add<D: CorpusDocument>(&mut self, doc: &D) {
+        let lang = doc.identification().label().as_str();
+        match self.languages.get_mut(lang) {
+            Some(stats) => stats.add(doc),
+            None => {
+                let mut stats = Stats::default();
+                stats.add(doc);
+                self.languages.insert(lang.to_string(), stats);
+            }
+        }
+    }
+
+    /// Compute statistics of a document stream, stopping at the first error.
+    pub fn from_documents<D, I>(documents: I) -> Result<Self, Error>
+    where
+        D: CorpusDocument,
+        I: IntoIterator<Item = Result<D, Error>>,
+    {
+        let mut report = Self::default();
+        for doc in documents {
+            report.add(&doc?);
+        }
+        Ok(report)
+    }
+
+    /// Compute statistics of several files, using up to `threads` threads (at least one).
+    ///
+    /// `open` opens a file as a document stream, for example [crate::v3::Reader]
+    /// or [crate::oscar_doc::Reader]. Per-file reports are then merged.
+    pub fn from_files<P, F, I, D>(paths: &[P], threads: usize, open: F) -> Result<Self, Error>
+    where
+        P: AsRef<Path> + Sync,
+        F: Fn(&Path) -> Result<I, Error> + Sync,
+        I: IntoIterator<Item = Result<D, Error>>,
+        D: CorpusDocument,
+    {
+        let next = AtomicUsize::new(0);
+        let report = Mutex::new(Self::default());
+        let worker = || -> Result<(), Error> {
+            loop {
+                let idx = next.fetch_add(1, Ordering::Relaxed);
+                let path = match paths.get(idx) {
+                    Some(path) => path.as_ref(),
+                    None => return Ok(()),
+                };
+                let partial = Self::from_documents(open(path)?)?;
+                report
+                    .lock()
+                    .map_err(|e| Error::Custom(e.to_string()))?
+                    .merge(&partial);
+            }
+        };
+
+        thread::scope(|s| {
+            let handles: Vec<_> = (0..threads.clamp(1, paths.len().max(1)))
+                .map(|_| s.spawn(worker))
+                .collect();
+            handles
+                .into_iter()
+                .try_for_each(|h| h.join().expect("statistics thread panicked"))
+        })?;
+
+        report
+            .into_inner()
+            .map_err(|e| Error::Custom(e.to_string()))
+    }
+
+    /// Merge a report computed on another shard.
+    pub fn merge(&mut self, other: &Self) {
+        for (lang, stats) in &other.languages {
+            self.languages.entry(lang.clone()).or_default().merge(stats);
+        }
+    }
+
+    /// Statistics of every language.
+    pub fn total(&self) -> Stats {
+        let mut total = Stats::default();
+        for stats in self.languages.values() {
+            total.merge(stats);
+        }
+        total
+    }
+
+    /// Serialize the report in (pretty-printed) JSON.
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Deserialize a report, for example to merge it.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use super::{Histogram, Report};
+    use crate::oscar_doc;
+    use crate::test_utils::{doc_in, with_warnings};
+    use crate::v3::Document;
+
+    fn doc(content: &str, lang: &str, harmful_pp: Option<f32>, categories: &[&str]) -> Document {
+        let mut doc = with_warnings(doc_in(content, lang, 0.5), &["tiny"]);
+        for category in categories {
+            doc.metadata_mut().insert_category(*category);
+        }
+        doc.metadata_mut().set_harmful_pp(harmful_pp);
+        doc
+    }
+
+    #[test]
+    fn test_v3() {
+        let docs = vec![
+            doc("foo bar\nbaz", "fr", Some(12.0), &["adult"]),
+            doc("été", "fr", None, &[]),
+            doc("hello", "en", Some(20000.0), &["adult", "gambling"]),
+        ];
+        let report = Report::from_documents(docs.into_iter().map(Ok)).unwrap();
+        let fr = &report.languages["fr"];
+        assert_eq!(fr.documents, 2);
+        assert_eq!(fr.bytes, 11 + 5);
+        assert_eq!(fr.lines, 3);
+        assert_eq!(fr.words, 4);
+        assert_eq!(fr.avg_prob(), 0.5);
+        assert_eq!(fr.quality_warnings["tiny"], 2);
+        assert_eq!(fr.harmful_pp.bins()[1], 1);
+        assert_eq!(fr.harmful_pp.missing(), 1);
+        assert!(fr.domains.is_empty());
+
+        let total = report.total();
+        assert_eq!(total.documents, 3);
+        assert_eq!(total.categories["adult"], 2);
+        assert_eq!(total.harmful_pp.bins().last(), Some(&1));
+        assert_eq!(Histogram::labels()[1], "[10, 25)");
+    }
+
+    #[test]
+    fn test_merge() {
+        let open = || {
+            let f = File::open("tests/res/data.jsonl").unwrap();
+            oscar_doc::Reader::new(BufReader::new(f))
+        };
+        let single = Report::from_documents(open()).unwrap();
+        assert!(single.languages["en"].domains["en.wikipedia.org"] > 0);
+
+        let mut merged = single.clone();
+        merged.merge(&single);
+        let total = merged.total();
+        assert_eq!(total.documents, 2 * single.total().documents);
+        assert_eq!(total.words, 2 * single.total().words);
+
+        let paths = ["tests/res/data.jsonl"; 3];
+        let parallel = Report::from_files(&paths, 2, |path| {
+            Ok(oscar_doc::Reader::new(BufReader::new(File::open(path)?)))
+        })
+        .unwrap();
+        assert_eq!(parallel.total().documents, 3 * single.total().documents);
+
+        let err = Report::from_files(&["does/not/exist"], 2, |path| {
+            Ok(oscar_doc::Reader::new(BufReader::new(File::open(path)?)))
+        });
+        assert!(err.is_err());
+
+        // roundtrip (probability sums may differ in their last digit)
+        let json = merged.to_json().unwrap();
+        let parsed = Report::from_json(&json).unwrap();
+        assert_eq!(
+            parsed.languages["en"].domains,
+            merged.languages["en"].domains
+        );
+        assert!((parsed.total().avg_prob() - total.avg_prob()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_markdown() {
+        let docs = vec![doc("foo", "fr", Some(12.0), &["adult"])];
+        let report = Report::from_documents(docs.into_iter().map(Ok)).unwrap();
+        let md = report.to_markdown(5);
+        assert!(md.contains("| Language | Documents | Bytes | Lines | Words | Avg. prob |"));
+        assert!(md.contains("| fr | 1 | 3 | 1 | 1 | 0.500 |"));
+        assert!(md.contains("| **total** | 1 | 3 | 1 | 1 | 0.500 |"));
+        assert!(md.contains("| adult | 1 |"));
+        assert!(md.contains("| [10, 25) | 1 |"));
+    }
+}
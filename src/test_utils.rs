@@ -0,0 +1,43 @@
+//! Document fixtures shared by unit tests.
+use oxilangtag::LanguageTag;
+use url::Url;
+
+use crate::common::Identification;
+use crate::v3::{Document, Metadata};
+
+/// Identification of `lang` with probability `prob`.
+pub(crate) fn id(lang: &str, prob: f32) -> Identification<String> {
+    Identification::new(LanguageTag::parse(lang.to_string()).unwrap(), prob)
+}
+
+/// Document with the builder defaults: generated record id and date, unknown language.
+pub(crate) fn doc(content: impl Into<String>) -> Document {
+    Document::builder(content).build().unwrap()
+}
+
+/// Document identified as `lang` with probability `prob`, as is each of its lines.
+pub(crate) fn doc_in(content: &str, lang: &str, prob: f32) -> Document {
+    let metadata = Metadata::builder(id(lang, prob))
+        .sentence_identifications(vec![Some(id(lang, prob)); content.lines().count()])
+        .build()
+        .unwrap();
+    Document::builder(content)
+        .metadata(metadata)
+        .build()
+        .unwrap()
+}
+
+/// Sets the target URI of `doc`.
+pub(crate) fn with_url(mut doc: Document, url: &str) -> Document {
+    doc.warc_headers_mut()
+        .set_target_uri(&Url::parse(url).unwrap());
+    doc
+}
+
+/// Adds quality warnings to `doc`.
+pub(crate) fn with_warnings(mut doc: Document, warnings: &[&str]) -> Document {
+    for warning in warnings {
+        doc.metadata_mut().add_quality_warning((*warning).into());
+    }
+    doc
+}
@@ -0,0 +1,222 @@
+/*! Document transformations
+
+[LineFilter] strips lines that are not in the document language (or that have a low identification probability),
+to produce cleaner monolingual documents.
+Works on any [CorpusDocument].
+
+```
+use oscar_io::transform::{LineFilter, LineFilterOutcome};
+# use oscar_io::common::Identification;
+# use oscar_io::v3::{Document, Metadata};
+# use oxilangtag::LanguageTag;
+# let id = |l: &str| Some(Identification::new(LanguageTag::parse(l.to_string()).unwrap(), 0.9));
+# let metadata = Metadata::builder(id("fr").unwrap())
+#     .sentence_identifications(vec![id("fr"), id("en")])
+#     .build()
+#     .unwrap();
+# let mut doc = Document::builder("bonjour\nhello").metadata(metadata).build().unwrap();
+
+let filter = LineFilter::default().with_min_prob(0.8).with_min_lines(1);
+match filter.apply(&mut doc).unwrap() {
+    LineFilterOutcome::Kept { removed } => assert_eq!(removed, 1),
+    LineFilterOutcome::Dropped => unreachable!(),
+}
+assert_eq!(doc.content(), "bonjour");
+```
+!*/
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::{CorpusDocument, DocumentLines, Identification};
+use crate::error::Error;
+
+/// Extension key where [RemovedLines] are recorded.
+pub const REMOVED_LINES_KEY: &str = "removed_lines";
+
+/// Summary of the lines removed by a [LineFilter], stored in the document extensions (see [REMOVED_LINES_KEY]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemovedLines {
+    /// Number of removed lines.
+    pub lines: usize,
+    /// Number of removed bytes (without line breaks).
+    pub bytes: usize,
+    /// Number of removed lines per language.
+    pub languages: BTreeMap<String, usize>,
+    /// Number of removed lines that had no identification.
+    pub unidentified: usize,
+    /// Document identification before filtering.
+    pub original_identification: Identification<String>,
+}
+
+/// Result of [LineFilter::apply].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineFilterOutcome {
+    /// The document has been kept, with `removed` lines removed.
+    Kept { removed: usize },
+    /// Too little remains, the document should be dropped. It is left untouched.
+    Dropped,
+}
+
+/// Removes lines whose identification doesn't match the document language, or whose probability is too low.
+///
+/// By default, lines have to match the document label, with no probability threshold,
+/// and documents are never dropped.
+#[derive(Debug, Clone, Default)]
+pub struct LineFilter {
+    lang: Option<String>,
+    min_prob: f32,
+    min_lines: usize,
+    min_kept_ratio: f32,
+}
+
+impl LineFilter {
+    /// Keep lines in `lang` rather than in the document language.
+    pub fn with_lang(mut self, lang: impl Into<String>) -> Self {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    /// Remove lines identified with a probability below `min_prob`.
+    pub fn with_min_prob(mut self, min_prob: f32) -> Self {
+        self.min_prob = min_prob;
+        self
+    }
+
+    /// Drop documents that have less than `min_lines` lines left.
+    pub fn with_min_lines(mut self, min_lines: usize) -> Self {
+        self.min_lines = min_lines;
+        self
+    }
+
+    /// Drop documents that keep less than `min_kept_ratio` of their bytes.
+    pub fn with_min_kept_ratio(mut self, min_kept_ratio: f32) -> Self {
+        self.min_kept_ratio = min_kept_ratio;
+        self
+    }
+
+    fn keep(&self, lang: &str, identification: Option<&Identification<String>>) -> bool {
+        identification
+            .map(|id| id.label().as_str() == lang && *id.prob() >= self.min_prob)
+            .unwrap_or(false)
+    }
+
+    /// Filter lines of `doc`.
+    ///
+    /// If lines are removed, the document identification is recomputed from the remaining lines
+    /// and a [RemovedLines] summary is stored in the document extensions.
+    /// Fails if lines and sentence identifications are not aligned.
+    pub fn apply<D: CorpusDocument>(&self, doc: &mut D) -> Result<LineFilterOutcome, Error> {
+        let lang = self
+            .lang
+            .clone()
+            .unwrap_or_else(|| doc.identification().label().to_string());
+
+        let mut total_bytes = 0;
+        let mut kept_lines = 0;
+        let mut kept_bytes = 0;
+        let mut languages = BTreeMap::new();
+        let mut unidentified = 0;
+        for (line, id) in doc.lines()? {
+            total_bytes += line.len();
+            if self.keep(&lang, id) {
+                kept_lines += 1;
+                kept_bytes += line.len();
+            } else {
+                match id {
+                    Some(id) => *languages.entry(id.label().to_string()).or_insert(0) += 1,
+                    None => unidentified += 1,
+                }
+            }
+        }
+
+        let kept_ratio = if total_bytes == 0 {
+            1.0
+        } else {
+            kept_bytes as f32 / total_bytes as f32
+        };
+        if kept_lines < self.min_lines || kept_ratio < self.min_kept_ratio {
+            return Ok(LineFilterOutcome::Dropped);
+        }
+
+        let original_identification = doc.identification().clone();
+        let removed = doc.retain_lines(|_, _, id| self.keep(&lang, id))?;
+        if removed > 0 {
+            doc.recompute_identification()?;
+            let summary = RemovedLines {
+                lines: removed,
+                bytes: total_bytes - kept_bytes,
+                languages,
+                unidentified,
+                original_identification,
+            };
+            doc.extensions_mut().set(REMOVED_LINES_KEY, &summary)?;
+        }
+
+        Ok(LineFilterOutcome::Kept { removed })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineFilter, LineFilterOutcome, RemovedLines, REMOVED_LINES_KEY};
+    use crate::common::{CorpusDocument, DocumentLines};
+    use crate::test_utils::id;
+    use crate::v3::{Document, Metadata};
+
+    fn get_doc() -> Document {
+        let ids = vec![
+            Some(id("fr", 0.9)),
+            Some(id("en", 0.9)),
+            None,
+            Some(id("fr", 0.5)),
+        ];
+        let metadata = Metadata::builder(id("fr", 0.6))
+            .sentence_identifications(ids)
+            .build()
+            .unwrap();
+        Document::builder("bonjour\nhello\n1234\nau revoir")
+            .metadata(metadata)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut doc = get_doc();
+        let outcome = LineFilter::default().with_min_prob(0.8).apply(&mut doc);
+        assert_eq!(outcome.unwrap(), LineFilterOutcome::Kept { removed: 3 });
+        assert_eq!(doc.content(), "bonjour");
+        assert_eq!(doc.identification(), &id("fr", 0.9));
+
+        let removed: RemovedLines = doc.extensions().get(REMOVED_LINES_KEY).unwrap().unwrap();
+        assert_eq!(removed.lines, 3);
+        assert_eq!(removed.bytes, 5 + 4 + 9);
+        assert_eq!(removed.unidentified, 1);
+        assert_eq!(removed.languages.get("en"), Some(&1));
+        assert_eq!(removed.languages.get("fr"), Some(&1));
+        assert_eq!(removed.original_identification, id("fr", 0.6));
+    }
+
+    #[test]
+    fn test_nothing_removed() {
+        let mut doc = get_doc();
+        doc.keep_language("fr").unwrap();
+        let before = doc.clone();
+        let outcome = LineFilter::default().apply(&mut doc).unwrap();
+        assert_eq!(outcome, LineFilterOutcome::Kept { removed: 0 });
+        assert_eq!(doc, before);
+    }
+
+    #[test]
+    fn test_drop() {
+        let mut doc = get_doc();
+        let before = doc.clone();
+        let filter = LineFilter::default().with_lang("en").with_min_lines(2);
+        assert_eq!(filter.apply(&mut doc).unwrap(), LineFilterOutcome::Dropped);
+        assert_eq!(doc, before);
+
+        let filter = LineFilter::default().with_min_kept_ratio(0.8);
+        assert_eq!(filter.apply(&mut doc).unwrap(), LineFilterOutcome::Dropped);
+    }
+}
@@ -0,0 +1,511 @@
+/*! Sidecar join.
+
+Merges per-document annotations (toxicity, PII counts, topic labels...) read from a sidecar (see [super::SidecarReader])
+into the metadata of a document stream, matching them by WARC record id.
+Known metadata fields (`harmful_pp`, `categories`...) are replaced, other ones are added as [crate::common::Extensions].
+Merged metadata is validated (probabilities, one sentence identification per line): invalid entries yield an error.
+
+Two modes are available:
+
+- [SidecarJoin::hash] loads the sidecar in memory, and works on any document order,
+- [SidecarJoin::sorted] streams the sidecar, but both the corpus and the sidecar must be sorted by record id
+  (see [crate::sort::ExternalSort]).
+
+```no_run
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use oscar_io::v3::{FieldConflict, Reader, SidecarJoin, SidecarReader, Writer, WriterTrait};
+use oxilangtag::LanguageTag;
+
+let docs = Reader::from_path(Path::new("fr_meta.jsonl")).unwrap();
+let sidecar = SidecarReader::new(BufReader::new(File::open("fr_toxicity.jsonl").unwrap()));
+let mut writer = Writer::new(
+    Path::new("out/"),
+    LanguageTag::parse("fr".to_string()).unwrap(),
+    None,
+    None,
+)
+.unwrap();
+SidecarJoin::sorted(docs, sidecar)
+    .with_conflict(FieldConflict::Overwrite)
+    .write(&mut writer)
+    .unwrap();
+writer.flush().unwrap();
+```
+!*/
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::iter::Peekable;
+
+use serde_json::{Map, Value};
+
+use crate::common::CorpusDocument;
+use crate::error::Error;
+
+use super::{validation, Document, WriterTrait};
+
+/// Sidecar entry fields.
+pub type Fields = Map<String, Value>;
+
+/// What to do when a sidecar field is already set (not `null`) to another value in the document metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldConflict {
+    /// Keep the document value.
+    Keep,
+    /// Use the sidecar value.
+    Overwrite,
+    /// Yield an error for the document, and continue.
+    Error,
+}
+
+/// What to do with documents that have no sidecar entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingEntry {
+    /// Keep the document unchanged.
+    Keep,
+    /// Silently skip the document.
+    Skip,
+    /// Yield an error for the document, and continue.
+    Error,
+}
+
+enum Entries<S: Iterator> {
+    Hash(HashMap<String, Fields>),
+    Sorted {
+        entries: Peekable<S>,
+        last: Option<String>,
+        unmatched: u64,
+    },
+}
+
+/// Merges sidecar entries into documents.
+///
+/// Each entry is merged at most once, into the first document with its record id.
+///
+/// When the entry of a document can't be looked up (malformed sidecar line, unsorted input...),
+/// the error is yielded and the document follows, unchanged.
+pub struct SidecarJoin<I, S: Iterator> {
+    documents: I,
+    entries: Entries<S>,
+    conflict: FieldConflict,
+    missing: MissingEntry,
+    last_document: Option<String>,
+    failed: Option<Document>,
+}
+
+impl<I> SidecarJoin<I, std::iter::Empty<Result<(String, Fields), Error>>>
+where
+    I: Iterator<Item = Result<Document, Error>>,
+{
+    /// Join documents with a sidecar held in memory (see [super::SidecarReader::into_map]).
+    pub fn hash(
+        documents: impl IntoIterator<IntoIter = I>,
+        sidecar: HashMap<String, Fields>,
+    ) -> Self {
+        Self::new(documents.into_iter(), Entries::Hash(sidecar))
+    }
+}
+
+impl<I, S> SidecarJoin<I, S>
+where
+    I: Iterator<Item = Result<Document, Error>>,
+    S: Iterator<Item = Result<(String, Fields), Error>>,
+{
+    /// Join documents with a streamed sidecar. Both must be sorted by record id, otherwise an error is yielded.
+    ///
+    /// Documents without record id can't be checked and are treated as having no entry.
+    pub fn sorted(
+        documents: impl IntoIterator<IntoIter = I>,
+        sidecar: impl IntoIterator<IntoIter = S>,
+    ) -> Self {
+        Self::new(
+            documents.into_iter(),
+            Entries::Sorted {
+                entries: sidecar.into_iter().peekable(),
+                last: None,
+                unmatched: 0,
+            },
+        )
+    }
+
+    fn new(documents: I, entries: Entries<S>) -> Self {
+        Self {
+            documents,
+            entries,
+            conflict: FieldConflict::Overwrite,
+            missing: MissingEntry::Keep,
+            last_document: None,
+            failed: None,
+        }
+    }
+
+    /// Set behavior for conflicting fields. Defaults to [FieldConflict::Overwrite].
+    pub fn with_conflict(mut self, conflict: FieldConflict) -> Self {
+        self.conflict = conflict;
+        self
+    }
+
+    /// Set behavior for documents lacking a sidecar entry. Defaults to [MissingEntry::Keep].
+    pub fn with_missing(mut self, missing: MissingEntry) -> Self {
+        self.missing = missing;
+        self
+    }
+
+    /// Number of sidecar entries that have not been merged into a document (yet).
+    ///
+    /// In sorted mode, entries are only counted once they have been read.
+    pub fn unmatched_entries(&self) -> u64 {
+        match &self.entries {
+            Entries::Hash(map) => map.len() as u64,
+            Entries::Sorted { unmatched, .. } => *unmatched,
+        }
+    }
+
+    /// Write joined documents, stopping at the first error. The writer is not flushed.
+    ///
+    /// Returns the number of documents written.
+    pub fn write<W: WriterTrait<Item = Document>>(self, writer: &mut W) -> Result<u64, Error> {
+        let mut written = 0;
+        for doc in self {
+            writer.write(vec![doc?])?;
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Find the entry of `record_id`.
+    fn entry(&mut self, record_id: &str) -> Result<Option<Fields>, Error> {
+        let (entries, last, unmatched) = match &mut self.entries {
+            Entries::Hash(map) => return Ok(map.remove(record_id)),
+            Entries::Sorted {
+                entries,
+                last,
+                unmatched,
+            } => (entries, last, unmatched),
+        };
+
+        if self.last_document.as_deref() > Some(record_id) {
+            return Err(Error::Custom(format!(
+                "documents are not sorted by record id: {record_id} after {}",
+                self.last_document.as_deref().unwrap_or_default()
+            )));
+        }
+        self.last_document = Some(record_id.to_string());
+
+        loop {
+            let ordering = match entries.peek() {
+                None => return Ok(None),
+                Some(Err(_)) => Ordering::Less,
+                Some(Ok((id, _))) => id.as_str().cmp(record_id),
+            };
+            if ordering == Ordering::Greater {
+                return Ok(None);
+            }
+            let (id, fields) = entries.next().expect("peeked")?;
+            if last.as_deref() > Some(id.as_str()) {
+                return Err(Error::Custom(format!(
+                    "sidecar is not sorted by record id: {id} after {}",
+                    last.as_deref().unwrap_or_default()
+                )));
+            }
+            *last = Some(id);
+            if ordering == Ordering::Equal {
+                return Ok(Some(fields));
+            }
+            *unmatched += 1;
+        }
+    }
+
+    /// Merge `fields` into the document metadata, leaving it unchanged if the result is not valid.
+    fn merge(&self, doc: &mut Document, fields: Fields) -> Result<(), Error> {
+        let mut metadata = match serde_json::to_value(doc.metadata())? {
+            Value::Object(metadata) => metadata,
+            _ => unreachable!("metadata is serialized as an object"),
+        };
+        for (field, value) in fields {
+            match metadata.get(&field) {
+                Some(current) if !current.is_null() && *current != value => match self.conflict {
+                    FieldConflict::Keep => continue,
+                    FieldConflict::Overwrite => (),
+                    FieldConflict::Error => {
+                        return Err(Error::Custom(format!(
+                            "conflicting values for field {field} of record {}",
+                            doc.record_id().unwrap_or_default()
+                        )))
+                    }
+                },
+                _ => (),
+            }
+            metadata.insert(field, value);
+        }
+        let metadata = serde_json::from_value(Value::Object(metadata))?;
+        validation::into_result(validation::validate_content(doc.content(), &metadata))?;
+        *doc.metadata_mut() = metadata;
+        Ok(())
+    }
+}
+
+impl<I, S> Iterator for SidecarJoin<I, S>
+where
+    I: Iterator<Item = Result<Document, Error>>,
+    S: Iterator<Item = Result<(String, Fields), Error>>,
+{
+    type Item = Result<Document, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(doc) = self.failed.take() {
+            return Some(Ok(doc));
+        }
+        loop {
+            let mut doc = match self.documents.next() {
+                Some(Ok(doc)) => doc,
+                other => {
+                    // count remaining entries
+                    if let (
+                        None,
+                        Entries::Sorted {
+                            entries, unmatched, ..
+                        },
+                    ) = (&other, &mut self.entries)
+                    {
+                        *unmatched += entries.by_ref().count() as u64;
+                    }
+                    return other;
+                }
+            };
+
+            let entry = match CorpusDocument::record_id(&doc).map(|id| id.into_owned()) {
+                Some(id) => match self.entry(&id) {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        self.failed = Some(doc);
+                        return Some(Err(e));
+                    }
+                },
+                None => None,
+            };
+            match entry {
+                Some(fields) => {
+                    return Some(self.merge(&mut doc, fields).map(|_| doc));
+                }
+                None => match self.missing {
+                    MissingEntry::Keep => return Some(Ok(doc)),
+                    MissingEntry::Skip => continue,
+                    MissingEntry::Error => {
+                        return Some(Err(Error::Custom(format!(
+                            "no sidecar entry for record {}",
+                            doc.record_id().unwrap_or_default()
+                        ))))
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use oxilangtag::LanguageTag;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::{FieldConflict, Fields, MissingEntry, SidecarJoin};
+    use crate::error::Error;
+    use crate::test_utils;
+    use crate::v3::{Document, SidecarReader, Writer, WriterTrait};
+
+    fn doc(id: u128, harmful_pp: Option<f32>) -> Document {
+        let mut doc = test_utils::doc(format!("doc {id}"));
+        doc.warc_headers_mut().set_record_id(&Uuid::from_u128(id));
+        doc.metadata_mut().set_harmful_pp(harmful_pp);
+        doc
+    }
+
+    fn record_id(id: u128) -> String {
+        format!("<urn:uuid:{}>", Uuid::from_u128(id))
+    }
+
+    fn jsonl(entries: &[(u128, serde_json::Value)]) -> String {
+        entries
+            .iter()
+            .map(|(id, fields)| {
+                let mut fields = fields.clone();
+                fields["warc-record-id"] = json!(record_id(*id));
+                format!("{fields}\n")
+            })
+            .collect()
+    }
+
+    fn entries(sidecar: &str) -> SidecarReader<&[u8], Fields> {
+        SidecarReader::new(sidecar.as_bytes())
+    }
+
+    #[test]
+    fn test_hash() {
+        let docs = || vec![Ok(doc(2, Some(10.0))), Ok(doc(1, None)), Ok(doc(3, None))];
+        let sidecar = jsonl(&[
+            (1, json!({"harmful_pp": 5.0, "toxicity": 0.1})),
+            (2, json!({"harmful_pp": 20.0, "pii": 2})),
+            (4, json!({"toxicity": 0.9})),
+        ]);
+
+        let mut join = SidecarJoin::hash(docs(), entries(&sidecar).into_map().unwrap())
+            .with_conflict(FieldConflict::Keep);
+        let joined: Vec<Document> = join.by_ref().collect::<Result<_, _>>().unwrap();
+        assert_eq!(join.unmatched_entries(), 1);
+        assert_eq!(joined.len(), 3);
+        assert_eq!(joined[0].metadata().harmful_pp(), Some(10.0));
+        assert_eq!(
+            joined[0].metadata().get_extension::<u32>("pii").unwrap(),
+            Some(2)
+        );
+        assert_eq!(joined[1].metadata().harmful_pp(), Some(5.0));
+        assert_eq!(
+            joined[1]
+                .metadata()
+                .get_extension::<f64>("toxicity")
+                .unwrap(),
+            Some(0.1)
+        );
+        assert!(joined[2].metadata().extensions().is_empty());
+
+        let joined: Vec<Result<Document, Error>> =
+            SidecarJoin::hash(docs(), entries(&sidecar).into_map().unwrap())
+                .with_conflict(FieldConflict::Error)
+                .with_missing(MissingEntry::Skip)
+                .collect();
+        assert_eq!(joined.len(), 2);
+        assert!(joined[0].is_err());
+        assert_eq!(
+            joined[1].as_ref().unwrap().metadata().harmful_pp(),
+            Some(5.0)
+        );
+
+        let joined = SidecarJoin::hash(docs(), entries(&sidecar).into_map().unwrap())
+            .with_missing(MissingEntry::Error)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            joined[0].as_ref().unwrap().metadata().harmful_pp(),
+            Some(20.0)
+        );
+        assert!(joined[2].is_err());
+    }
+
+    #[test]
+    fn test_sorted() {
+        let docs = vec![Ok(doc(1, None)), Ok(doc(3, None)), Ok(doc(4, None))];
+        let sidecar = jsonl(&[
+            (0, json!({"pii": 0})),
+            (1, json!({"pii": 1})),
+            (2, json!({"pii": 2})),
+            (4, json!({"pii": 4})),
+            (5, json!({"pii": 5})),
+        ]);
+        let mut join = SidecarJoin::sorted(docs, entries(&sidecar));
+        let pii: Vec<Option<u32>> = join
+            .by_ref()
+            .map(|doc| doc.unwrap().metadata().get_extension("pii").unwrap())
+            .collect();
+        assert_eq!(pii, vec![Some(1), None, Some(4)]);
+        assert_eq!(join.unmatched_entries(), 3);
+
+        // unsorted documents are kept, after the error
+        let docs = vec![Ok(doc(3, None)), Ok(doc(1, None))];
+        let joined: Vec<_> = SidecarJoin::sorted(docs, entries(&sidecar)).collect();
+        assert_eq!(joined.len(), 3);
+        assert!(joined[0].is_ok());
+        assert!(joined[1].is_err());
+        assert_eq!(joined[2].as_ref().unwrap().content(), "doc 1");
+
+        // unsorted sidecar
+        let sidecar = jsonl(&[(2, json!({"pii": 2})), (1, json!({"pii": 1}))]);
+        let joined: Vec<_> =
+            SidecarJoin::sorted(vec![Ok(doc(4, None))], entries(&sidecar)).collect();
+        assert_eq!(joined.len(), 2);
+        assert!(joined[0].is_err());
+        assert!(joined[1].is_ok());
+    }
+
+    #[test]
+    fn test_malformed_sidecar() {
+        let sidecar = format!(
+            "{}not json\n{}",
+            jsonl(&[(1, json!({"pii": 1}))]),
+            jsonl(&[(3, json!({"pii": 3}))])
+        );
+        let docs = vec![Ok(doc(1, None)), Ok(doc(2, None)), Ok(doc(3, None))];
+        let mut join = SidecarJoin::sorted(docs, entries(&sidecar));
+        let joined: Vec<_> = join.by_ref().collect();
+
+        // the document whose lookup hit the malformed line is yielded after the error
+        assert_eq!(joined.len(), 4);
+        assert!(joined[1].is_err());
+        let pii: Vec<Option<u32>> = [&joined[0], &joined[2], &joined[3]]
+            .iter()
+            .map(|doc| {
+                let doc = doc.as_ref().unwrap();
+                doc.metadata().get_extension("pii").unwrap()
+            })
+            .collect();
+        assert_eq!(pii, vec![Some(1), None, Some(3)]);
+        assert_eq!(join.unmatched_entries(), 0);
+    }
+
+    #[test]
+    fn test_invalid_metadata() {
+        let sidecar = jsonl(&[
+            (1, json!({"sentence_identifications": [null, null]})),
+            (2, json!({"identification": {"label": "en", "prob": 2.0}})),
+            (
+                3,
+                json!({"sentence_identifications": [{"label": "en", "prob": 0.5}]}),
+            ),
+        ]);
+        let docs = vec![Ok(doc(1, None)), Ok(doc(2, None)), Ok(doc(3, None))];
+        let joined: Vec<_> =
+            SidecarJoin::hash(docs, entries(&sidecar).into_map().unwrap()).collect();
+        assert!(matches!(joined[0], Err(Error::Validation(_))));
+        assert!(matches!(joined[1], Err(Error::Validation(_))));
+        assert_eq!(
+            joined[2]
+                .as_ref()
+                .unwrap()
+                .metadata()
+                .sentence_identifications()[0]
+                .as_ref()
+                .map(|id| *id.prob()),
+            Some(0.5)
+        );
+    }
+
+    #[test]
+    fn test_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = Writer::new(
+            dir.path(),
+            LanguageTag::parse("en".to_string()).unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+        let sidecar = jsonl(&[(1, json!({"topic": "sports"}))]);
+        let n = SidecarJoin::sorted(vec![Ok(doc(1, None)), Ok(doc(2, None))], entries(&sidecar))
+            .write(&mut writer)
+            .unwrap();
+        assert_eq!(n, 2);
+        writer.flush().unwrap();
+
+        let written = std::fs::read_to_string(dir.path().join("en.jsonl")).unwrap();
+        let docs: Vec<Document> = written
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            docs[0].metadata().get_extension::<String>("topic").unwrap(),
+            Some("sports".to_string())
+        );
+        assert_eq!(docs[0].warc_id().unwrap(), record_id(1));
+    }
+}
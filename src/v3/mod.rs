@@ -1,10 +1,21 @@
+mod join;
 mod reader;
+mod sidecar;
 mod types;
+mod validation;
+mod wet;
 mod writer;
 
+pub use crate::error::ValidationError;
+pub use join::{FieldConflict, Fields, MissingEntry, SidecarJoin};
 pub use reader::Reader;
+pub use sidecar::SidecarReader;
+pub use types::builder::{DocumentBuilder, MetadataBuilder};
 pub use types::document::Document;
 pub use types::document::Metadata;
+pub use types::tags::{Category, QualityWarning};
+pub use wet::{MissingMetadata, MissingRecord, WetFileReader, WetReader};
 pub use writer::Comp;
+pub(crate) use writer::NewWriter;
 pub use writer::Writer;
 pub use writer::WriterTrait;
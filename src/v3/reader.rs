@@ -94,7 +94,6 @@ mod tests {
 
         let doc_no_newline: String = doc
             .lines()
-            .into_iter()
             .map(|line| line.trim_matches(char::is_whitespace))
             .collect();
         let mut ret = String::new();
@@ -0,0 +1,113 @@
+/*! Metadata sidecar reader.
+
+A sidecar is a JSONL file holding per-document data, keyed by WARC record id:
+each line is a JSON object with a `warc-record-id` field, the other fields being the data itself.
+
+```json
+{"warc-record-id":"<urn:uuid:4c2d4cbb-24ef-4885-9516-d131fc15af2e>","identification":{"label":"fr","prob":0.96},"sentence_identifications":[{"label":"fr","prob":0.95}]}
+```
+!*/
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::error::Error;
+
+#[derive(Deserialize)]
+struct SidecarEntry<T> {
+    #[serde(rename = "warc-record-id")]
+    record_id: String,
+    #[serde(flatten)]
+    data: T,
+}
+
+/// Streaming sidecar reader.
+/// Yields `(record_id, data)` pairs, `data` being deserialized from the remaining fields of each entry.
+pub struct SidecarReader<R: BufRead, T> {
+    r: R,
+    data_type: PhantomData<T>,
+}
+
+impl<R: BufRead, T: DeserializeOwned> SidecarReader<R, T> {
+    /// Create a new [SidecarReader].
+    pub fn new(r: R) -> Self {
+        Self {
+            r,
+            data_type: PhantomData,
+        }
+    }
+
+    /// Read the whole sidecar in memory, indexed by record id.
+    ///
+    /// Fails on duplicate record ids.
+    pub fn into_map(self) -> Result<HashMap<String, T>, Error> {
+        let mut map = HashMap::new();
+        for entry in self {
+            let (record_id, data) = entry?;
+            if map.contains_key(&record_id) {
+                return Err(Error::Custom(format!(
+                    "duplicate sidecar entry for record {record_id}"
+                )));
+            }
+            map.insert(record_id, data);
+        }
+        Ok(map)
+    }
+}
+
+impl<R: BufRead, T: DeserializeOwned> Iterator for SidecarReader<R, T> {
+    type Item = Result<(String, T), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut s = String::new();
+        loop {
+            s.clear();
+            match self.r.read_line(&mut s) {
+                Ok(0) => return None,
+                // skip blank lines
+                Ok(_) if s.trim().is_empty() => continue,
+                Ok(_) => {
+                    return Some(
+                        serde_json::from_str::<SidecarEntry<T>>(&s)
+                            .map(|entry| (entry.record_id, entry.data))
+                            .map_err(|e| e.into()),
+                    )
+                }
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::v3::Metadata;
+
+    use super::SidecarReader;
+
+    #[test]
+    fn test_read() {
+        let sidecar = r#"{"warc-record-id":"<urn:uuid:1>","identification":{"label":"fr","prob":0.9},"harmful_pp":null,"tlsh":null,"quality_warnings":null,"categories":null,"sentence_identifications":[]}
+
+{"warc-record-id":"<urn:uuid:2>","identification":{"label":"en","prob":0.8},"harmful_pp":12.0,"tlsh":null,"quality_warnings":["tiny"],"categories":null,"sentence_identifications":[null]}
+"#;
+        let entries: Vec<(String, Metadata)> = SidecarReader::new(sidecar.as_bytes())
+            .map(|x| x.unwrap())
+            .collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].0, "<urn:uuid:1>");
+        assert_eq!(entries[1].1.harmful_pp(), Some(12.0));
+    }
+
+    #[test]
+    fn test_duplicate() {
+        let sidecar = r#"{"warc-record-id":"<urn:uuid:1>","foo":1}
+{"warc-record-id":"<urn:uuid:1>","foo":2}
+"#;
+        let r: SidecarReader<_, serde_json::Value> = SidecarReader::new(sidecar.as_bytes());
+        assert!(r.into_map().is_err());
+    }
+}
@@ -0,0 +1,347 @@
+/*! Builders for [Metadata] and [Document].
+
+Builders check invariants when building, and return an [Error::Validation] holding every issue found.
+!*/
+use chrono::{DateTime, TimeZone, Utc};
+use serde_json::Value;
+use url::Url;
+use uuid::Uuid;
+use warc::{BufferedBody, Record, WarcHeader};
+
+use crate::common::{BlockDigest, Extensions, Identification, WarcHeaders};
+use crate::error::Error;
+use crate::v3::validation;
+
+use super::document::{Document, Metadata};
+use super::tags::{Category, QualityWarning};
+
+/// Builds a [Metadata].
+///
+/// ```
+/// use oscar_io::common::Identification;
+/// use oscar_io::v3::Metadata;
+/// use oxilangtag::LanguageTag;
+///
+/// let id = Identification::new(LanguageTag::parse("fr".to_string()).unwrap(), 0.9);
+/// let metadata = Metadata::builder(id.clone())
+///     .sentence_identifications(vec![Some(id), None])
+///     .harmful_pp(12.5)
+///     .quality_warning("short_sentences")
+///     .build()
+///     .unwrap();
+/// assert_eq!(metadata.harmful_pp(), Some(12.5));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MetadataBuilder {
+    identification: Identification<String>,
+    sentence_identifications: Vec<Option<Identification<String>>>,
+    harmful_pp: Option<f32>,
+    tlsh: Option<String>,
+    quality_warnings: Vec<QualityWarning>,
+    categories: Vec<Category>,
+    extensions: Extensions,
+}
+
+impl MetadataBuilder {
+    pub fn new(identification: Identification<String>) -> Self {
+        Self {
+            identification,
+            sentence_identifications: Vec::new(),
+            harmful_pp: None,
+            tlsh: None,
+            quality_warnings: Vec::new(),
+            categories: Vec::new(),
+            extensions: Extensions::default(),
+        }
+    }
+
+    /// Set line-level identifications, one per line of content.
+    pub fn sentence_identifications(
+        mut self,
+        sentence_identifications: Vec<Option<Identification<String>>>,
+    ) -> Self {
+        self.sentence_identifications = sentence_identifications;
+        self
+    }
+
+    pub fn harmful_pp(mut self, harmful_pp: f32) -> Self {
+        self.harmful_pp = Some(harmful_pp);
+        self
+    }
+
+    pub fn tlsh(mut self, tlsh: impl Into<String>) -> Self {
+        self.tlsh = Some(tlsh.into());
+        self
+    }
+
+    /// Add a quality warning.
+    pub fn quality_warning(mut self, warning: impl Into<QualityWarning>) -> Self {
+        self.quality_warnings.push(warning.into());
+        self
+    }
+
+    /// Add a category.
+    pub fn category(mut self, category: impl Into<Category>) -> Self {
+        self.categories.push(category.into());
+        self
+    }
+
+    /// Set an unknown field (see [Extensions]).
+    pub fn extension(mut self, key: impl Into<String>, value: Value) -> Self {
+        self.extensions.set_raw(key, value);
+        self
+    }
+
+    /// Build the metadata, checking that probabilities are in `[0, 1]` (up to 1.001, see [Metadata::validate]).
+    pub fn build(self) -> Result<Metadata, Error> {
+        let mut metadata = Metadata::new(&self.identification, &self.sentence_identifications);
+        metadata.set_harmful_pp(self.harmful_pp);
+        metadata.set_tlsh(self.tlsh);
+        for warning in self.quality_warnings {
+            metadata.add_quality_warning(warning);
+        }
+        if !self.categories.is_empty() {
+            metadata.set_category_list(Some(self.categories));
+        }
+        *metadata.extensions_mut() = self.extensions;
+
+        validation::into_result(validation::validate_metadata(&metadata))?;
+        Ok(metadata)
+    }
+}
+
+/// Builds a [Document].
+///
+/// Mandatory WARC headers that are not set are generated when building:
+/// a random `warc-record-id`, the current time as `warc-date`, `conversion` as `warc-type`,
+/// and `content-length` from the content.
+///
+/// ```
+/// use oscar_io::v3::{Document, Metadata};
+/// use url::Url;
+///
+/// let doc = Document::builder("")
+///     .target_uri(&Url::parse("https://example.com").unwrap())
+///     .metadata(Metadata::empty())
+///     .build()
+///     .unwrap();
+/// assert_eq!(doc.warc_headers().domain().unwrap().as_deref(), Some("example.com"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct DocumentBuilder {
+    content: String,
+    warc_headers: WarcHeaders,
+    metadata: Option<Metadata>,
+}
+
+impl DocumentBuilder {
+    /// Create a builder with empty headers.
+    ///
+    /// Metadata defaults to [Metadata::empty], with an unknown (`None`) sentence identification per line.
+    pub fn new(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            warc_headers: WarcHeaders::default(),
+            metadata: None,
+        }
+    }
+
+    pub fn metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Set all WARC headers at once, replacing previously set ones.
+    pub fn warc_headers(mut self, warc_headers: WarcHeaders) -> Self {
+        self.warc_headers = warc_headers;
+        self
+    }
+
+    /// Set a raw WARC header.
+    pub fn header(mut self, header: WarcHeader, value: impl Into<Vec<u8>>) -> Self {
+        self.warc_headers.insert(header, value.into());
+        self
+    }
+
+    pub fn record_id(mut self, id: &Uuid) -> Self {
+        self.warc_headers.set_record_id(id);
+        self
+    }
+
+    pub fn refers_to(mut self, id: &Uuid) -> Self {
+        self.warc_headers.set_refers_to(id);
+        self
+    }
+
+    pub fn date<Tz: TimeZone>(mut self, date: &DateTime<Tz>) -> Self
+    where
+        Tz::Offset: std::fmt::Display,
+    {
+        self.warc_headers.set_date(date);
+        self
+    }
+
+    pub fn target_uri(mut self, url: &Url) -> Self {
+        self.warc_headers.set_target_uri(url);
+        self
+    }
+
+    pub fn block_digest(mut self, digest: &BlockDigest) -> Self {
+        self.warc_headers.set_block_digest(digest);
+        self
+    }
+
+    pub fn identified_content_language<S: AsRef<str>>(mut self, langs: &[S]) -> Self {
+        self.warc_headers.set_identified_content_language(langs);
+        self
+    }
+
+    /// Build the document, generating missing mandatory headers.
+    ///
+    /// Checks headers, probabilities, and that there's one sentence identification per line.
+    pub fn build(mut self) -> Result<Document, Error> {
+        let headers = &mut self.warc_headers;
+        headers
+            .entry(WarcHeader::RecordID)
+            .or_insert_with(|| Record::<BufferedBody>::generate_record_id().into());
+        if !headers.contains_key(&WarcHeader::Date) {
+            headers.set_date(&Utc::now());
+        }
+        headers
+            .entry(WarcHeader::WarcType)
+            .or_insert_with(|| "conversion".into());
+        if !headers.contains_key(&WarcHeader::ContentLength) {
+            headers.set_content_length(self.content.len() as u64);
+        }
+
+        let metadata = self.metadata.unwrap_or_else(|| {
+            let lines = self.content.lines().count();
+            Metadata::new(Metadata::empty().identification(), &vec![None; lines])
+        });
+        let doc = Document::new(self.content, self.warc_headers, metadata);
+        doc.validate()?;
+        Ok(doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use oxilangtag::LanguageTag;
+    use serde_json::json;
+    use uuid::Uuid;
+    use warc::WarcHeader;
+
+    use crate::common::Identification;
+    use crate::error::Error;
+    use crate::v3::{Category, Document, Metadata, QualityWarning, ValidationError};
+
+    fn id(label: &str, prob: f32) -> Identification<String> {
+        Identification::new(LanguageTag::parse(label.to_string()).unwrap(), prob)
+    }
+
+    #[test]
+    fn test_metadata() {
+        let metadata = Metadata::builder(id("fr", 0.9))
+            .sentence_identifications(vec![Some(id("fr", 0.8)), None])
+            .tlsh("T1")
+            .quality_warning("tiny")
+            .quality_warning(QualityWarning::Noisy)
+            .quality_warning("tiny")
+            .category(Category::Blog)
+            .extension("toxicity", json!(0.1))
+            .build()
+            .unwrap();
+
+        assert_eq!(metadata.tlsh().map(String::as_str), Some("T1"));
+        assert_eq!(
+            metadata.quality_warnings(),
+            &[QualityWarning::Tiny, QualityWarning::Noisy]
+        );
+        assert_eq!(metadata.category_list(), &[Category::Blog]);
+        assert_eq!(
+            metadata.get_extension::<f64>("toxicity").unwrap(),
+            Some(0.1)
+        );
+    }
+
+    #[test]
+    fn test_metadata_invalid_prob() {
+        let res = Metadata::builder(id("fr", 1.5))
+            .sentence_identifications(vec![Some(id("fr", -0.1))])
+            .build();
+        match res {
+            Err(Error::Validation(errors)) => assert_eq!(
+                errors,
+                vec![
+                    ValidationError::ProbOutOfRange {
+                        line: None,
+                        prob: 1.5
+                    },
+                    ValidationError::ProbOutOfRange {
+                        line: Some(0),
+                        prob: -0.1
+                    },
+                ]
+            ),
+            other => panic!("expected a validation error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_document() {
+        let record_id = Uuid::parse_str("4c2d4cbb-24ef-4885-9516-d131fc15af2e").unwrap();
+        let metadata = Metadata::builder(id("en", 1.0))
+            .sentence_identifications(vec![Some(id("en", 1.0)), None])
+            .build()
+            .unwrap();
+        let doc = Document::builder("foo\nbar")
+            .record_id(&record_id)
+            .identified_content_language(&["eng"])
+            .metadata(metadata)
+            .build()
+            .unwrap();
+
+        let headers = doc.warc_headers();
+        assert_eq!(headers.record_id().unwrap(), Some(record_id));
+        assert_eq!(headers.content_length().unwrap(), Some(7));
+        assert_eq!(
+            headers.get_str(&WarcHeader::WarcType).unwrap(),
+            Some("conversion")
+        );
+        assert!(headers.date().unwrap().is_some());
+        assert_eq!(
+            headers.identified_content_language().unwrap().unwrap(),
+            vec!["eng"]
+        );
+    }
+
+    #[test]
+    fn test_document_line_mismatch() {
+        let res = Document::builder("foo\nbar")
+            .metadata(Metadata::empty())
+            .build();
+        match res {
+            Err(Error::Validation(errors)) => assert_eq!(
+                errors,
+                vec![ValidationError::SentenceCountMismatch {
+                    lines: 2,
+                    identifications: 0
+                }]
+            ),
+            other => panic!("expected a validation error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let metadata = Metadata::empty();
+        assert_eq!(metadata.identification().label().as_str(), "und");
+        assert_eq!(*metadata.identification().prob(), 0.0);
+        assert!(metadata.sentence_identifications().is_empty());
+        assert!(Document::builder("").build().is_ok());
+
+        let doc = Document::builder("foo\nbar").build().unwrap();
+        assert_eq!(doc.metadata().sentence_identifications(), &[None, None]);
+        assert_eq!(doc.metadata().identification().label().as_str(), "und");
+    }
+}
@@ -1,36 +1,50 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
 
 use oxilangtag::LanguageTag;
 
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde::Serialize;
 use warc::BufferedBody;
 use warc::Record;
 use warc::WarcHeader;
 
+use crate::common::CorpusDocument;
+use crate::common::Extensions;
 use crate::common::Identification as IdentificationGen;
+use crate::common::WarcHeaders;
+use crate::error::Error;
+use crate::v3::validation;
+
+use super::builder::{DocumentBuilder, MetadataBuilder};
+use super::tags::{self, Category, QualityWarning, TagSet};
 
 type Identification = IdentificationGen<String>;
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 
 /// OSCAR-specific metadata
 // TODO: make it a HashMap
-// TODO: make annotation/categories hashmaps
 /// Contains document metadata:
 /// - `identification` is the document-level language identification (see [Identification])
 /// - `harmful_pp` is the perplexiry of the document, related to a model trained to recognize adult documents
-/// - `quality_warnings` (ex-annotation) contains tags for some length/content based quality filters
-/// - `categories` contains categories based on the url of the document. Uses the ut1 blocklist as a base.
+/// - `quality_warnings` (ex-annotation) contains tags for some length/content based quality filters (see [QualityWarning])
+/// - `categories` contains categories based on the url of the document. Uses the ut1 blocklist as a base (see [Category]).
 /// - `sentence_identifiations` contains line-level identifications.
+///
+/// Unknown fields are kept in [Extensions] and serialized back after the known ones.
 pub struct Metadata {
     identification: Identification,
     harmful_pp: Option<f32>,
     tlsh: Option<String>,
-    quality_warnings: Option<Vec<String>>,
-    categories: Option<Vec<String>>,
+    #[serde(default)]
+    quality_warnings: Option<TagSet<QualityWarning>>,
+    #[serde(default)]
+    categories: Option<TagSet<Category>>,
     sentence_identifications: Vec<Option<Identification>>,
+    #[serde(flatten)]
+    extensions: Extensions,
 }
 
 impl Metadata {
@@ -45,32 +59,118 @@ impl Metadata {
             quality_warnings: None,
             categories: None,
             sentence_identifications: sentence_identifications.to_owned(),
+            extensions: Extensions::default(),
         }
     }
 
+    /// Metadata with an undetermined (`und`) identification with a 0.0 prob,
+    /// and no sentence identifications (matching an empty content).
+    pub fn empty() -> Self {
+        Self::new(
+            &Identification::new(LanguageTag::parse("und".to_string()).unwrap(), 0.0),
+            &[],
+        )
+    }
+
+    /// Start building a [Metadata] (see [MetadataBuilder]).
+    pub fn builder(identification: Identification) -> MetadataBuilder {
+        MetadataBuilder::new(identification)
+    }
+
+    /// Adds a quality warning from its string form. Does nothing if already present.
     pub fn add_annotation(&mut self, annotation: String) {
-        match &mut self.quality_warnings {
-            Some(anno) => anno.push(annotation),
-            None => self.quality_warnings = Some(vec![annotation]),
-        }
+        self.add_quality_warning(annotation.into());
+    }
+
+    /// Get the quality warnings, as strings.
+    #[deprecated(note = "use `Metadata::quality_warnings`")]
+    pub fn annotation(&self) -> Option<&Vec<String>> {
+        self.quality_warnings.as_ref().map(TagSet::strings)
+    }
+
+    /// Get the quality warnings (empty if there are none).
+    pub fn quality_warnings(&self) -> &[QualityWarning] {
+        tags::values(&self.quality_warnings)
+    }
+
+    /// Adds a quality warning.
+    /// Returns `false` if it was already present.
+    pub fn add_quality_warning(&mut self, warning: QualityWarning) -> bool {
+        tags::insert(&mut self.quality_warnings, warning)
+    }
+
+    /// Removes a quality warning.
+    /// Returns `false` if it was not present.
+    pub fn remove_quality_warning(&mut self, warning: &QualityWarning) -> bool {
+        tags::remove(&mut self.quality_warnings, warning)
+    }
+
+    /// Checks if the quality warning is present.
+    pub fn has_quality_warning(&self, warning: &QualityWarning) -> bool {
+        self.quality_warnings().contains(warning)
     }
 
+    /// Get a reference to the document-level identification.
+    pub fn identification(&self) -> &Identification {
+        &self.identification
+    }
+
+    /// Set the document-level identification.
+    pub fn set_identification(&mut self, identification: Identification) {
+        self.identification = identification;
+    }
+
+    /// Get the categories, as strings.
+    #[deprecated(note = "use `Metadata::category_list`")]
     pub fn categories(&self) -> Option<&Vec<String>> {
-        self.categories.as_ref()
+        self.categories.as_ref().map(TagSet::strings)
+    }
+
+    /// Get the categories (empty if there are none).
+    pub fn category_list(&self) -> &[Category] {
+        tags::values(&self.categories)
     }
+
+    /// Adds a category from its string form. Does nothing if already present.
     pub fn add_category(&mut self, category: String) {
-        match &mut self.categories {
-            Some(cat) => cat.push(category),
-            None => self.categories = Some(vec![category]),
-        }
+        self.insert_category(category);
+    }
+
+    /// Adds a category.
+    /// Returns `false` if it was already present.
+    pub fn insert_category(&mut self, category: impl Into<Category>) -> bool {
+        tags::insert(&mut self.categories, category.into())
+    }
+
+    /// Removes a category.
+    /// Returns `false` if it was not present.
+    pub fn remove_category(&mut self, category: &Category) -> bool {
+        tags::remove(&mut self.categories, category)
     }
+
+    /// Sets the categories from strings, removing duplicates.
+    #[deprecated(note = "use `Metadata::set_category_list`")]
     pub fn set_categories(&mut self, categories: Option<Vec<String>>) {
-        self.categories = categories;
+        self.set_category_list(
+            categories.map(|categories| categories.into_iter().map(Category::from).collect()),
+        );
     }
 
-    /// Get a reference to the metadata's annotation.
-    pub fn annotation(&self) -> Option<&Vec<String>> {
-        self.quality_warnings.as_ref()
+    /// Sets the categories, removing duplicates.
+    pub fn set_category_list(&mut self, categories: Option<Vec<Category>>) {
+        self.categories = categories.map(|categories| categories.into_iter().collect());
+    }
+
+    /// Checks if the category is present.
+    pub fn has_category(&self, category: &Category) -> bool {
+        self.category_list().contains(category)
+    }
+
+    /// Checks if the document has an adult category (see [Category::is_adult])
+    /// or an [QualityWarning::Adult] warning.
+    pub fn is_adult(&self) -> bool {
+        self.has_quality_warning(&QualityWarning::Adult)
+            || self.category_list().iter().any(Category::is_adult)
     }
 
     /// Get a reference to the metadata's sentence identifications.
@@ -99,12 +199,44 @@ impl Metadata {
     ) {
         self.sentence_identifications = sentence_identifications;
     }
+
+    /// Checks that probabilities are in `[0, 1]`.
+    /// Values up to 1.001 are accepted, as fastText probabilities can slightly exceed 1.
+    /// Returns an [Error::Validation] holding every issue found.
+    pub fn validate(&self) -> Result<(), Error> {
+        validation::into_result(validation::validate_metadata(self))
+    }
+
+    /// Get a reference to the unknown fields.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Get a mutable reference to the unknown fields.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Get an unknown field, deserialized as `T`.
+    pub fn get_extension<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Error> {
+        self.extensions.get(key)
+    }
+
+    /// Set an unknown field.
+    pub fn set_extension<T: Serialize>(
+        &mut self,
+        key: impl Into<String>,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.extensions.set(key, value)
+    }
 }
 
 impl Default for Metadata {
     /// default Metadata is English with 1.0 prob,
     /// no annotation and a single english sentence with 1.0 prob.
-    // TODO: Change to empty document with no id and no sentence id?
+    ///
+    /// See [Metadata::empty] for metadata that doesn't assume a language.
     fn default() -> Self {
         Self {
             identification: Identification::new(LanguageTag::parse("en".to_string()).unwrap(), 1.0),
@@ -116,71 +248,21 @@ impl Default for Metadata {
                 LanguageTag::parse("en".to_string()).unwrap(),
                 1.0,
             ))],
+            extensions: Extensions::default(),
         }
     }
 }
 
-// TODO make this a newtype so that getters can do the u8 conversion
-pub type WarcHeaders = HashMap<WarcHeader, Vec<u8>>;
-
-pub type WarcHeadersSer = HashMap<WarcHeader, String>;
-
 /// A Document is a structure holding content, WARC headers and OSCAR-specific metadata.
 /// - TODO: Change warc_headers from [RawRecordHeader] to [warc::Record] with [warc::EmptyBody]?
-/// This way we shouldn't have to parse strings or use unwrap on [RawRecordHeader].
-#[derive(Serialize, Deserialize, Clone, PartialEq)]
-#[serde(from = "DocumentSer", into = "DocumentSer")]
+///   This way we shouldn't have to parse strings or use unwrap on [RawRecordHeader].
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct Document {
     content: String,
     warc_headers: WarcHeaders,
     metadata: Metadata,
 }
 
-#[derive(Serialize, Deserialize)]
-/// Serializable version of [Document].
-struct DocumentSer {
-    content: String,
-    warc_headers: WarcHeadersSer,
-    metadata: Metadata,
-}
-
-impl DocumentSer {
-    // pub fn get_schema() -> Result<String, Error> {
-    //     serde_json::to_string_pretty(&schemars::schema_for!(Self)).map_err(Error::Serde)
-    // }
-}
-impl From<Document> for DocumentSer {
-    fn from(d: Document) -> Self {
-        let warc_headers = d
-            .warc_headers
-            .into_iter()
-            .map(|(k, v)| (k, String::from_utf8_lossy(&v).into_owned()))
-            .collect();
-
-        Self {
-            content: d.content,
-            warc_headers,
-            metadata: d.metadata,
-        }
-    }
-}
-
-impl From<DocumentSer> for Document {
-    fn from(d: DocumentSer) -> Self {
-        let warc_headers = d
-            .warc_headers
-            .into_iter()
-            .map(|(k, v)| (k, v.as_bytes().to_vec()))
-            .collect();
-
-        Self {
-            content: d.content,
-            warc_headers,
-            metadata: d.metadata,
-        }
-    }
-}
-
 impl Document {
     pub fn new(content: String, warc_headers: WarcHeaders, metadata: Metadata) -> Self {
         Self {
@@ -190,14 +272,21 @@ impl Document {
         }
     }
 
-    // pub fn get_schema() -> Result<String, Error> {
-    //     DocumentSer::get_schema()
-    // }
+    /// Start building a [Document] (see [DocumentBuilder]).
+    pub fn builder(content: impl Into<String>) -> DocumentBuilder {
+        DocumentBuilder::new(content)
+    }
+
+    /// Get the JSON Schema of serialized documents, pretty-printed.
+    pub fn get_schema() -> Result<String, Error> {
+        crate::schema::Format::V3.to_json()
+    }
+
     /// Instantiate a Document from a record and a related metadata.
     pub fn from_record(record: Record<BufferedBody>, metadata: Metadata) -> Self {
         let (header, body) = record.into_raw_parts();
         let content = String::from_utf8_lossy(&body).into_owned();
-        let warc_headers = header.headers;
+        let warc_headers = header.headers.into();
 
         Self {
             content,
@@ -216,9 +305,13 @@ impl Document {
         &self.content
     }
 
-    /// get warc record id
-    pub fn warc_id(&self) -> Cow<str> {
-        String::from_utf8_lossy(self.warc_headers.get(&WarcHeader::RecordID).unwrap())
+    /// get warc record id, if present.
+    ///
+    /// The id is not checked, use [Document::validate] for that.
+    pub fn warc_id(&self) -> Option<Cow<'_, str>> {
+        self.warc_headers
+            .get(&WarcHeader::RecordID)
+            .map(|id| String::from_utf8_lossy(id))
     }
 
     /// Get a reference to the document's warc headers.
@@ -226,6 +319,22 @@ impl Document {
         &self.warc_headers
     }
 
+    /// Get a mutable reference to the document's warc headers.
+    pub fn warc_headers_mut(&mut self) -> &mut WarcHeaders {
+        &mut self.warc_headers
+    }
+
+    /// Checks that:
+    /// - mandatory WARC headers (record id, content length, date and type) are present,
+    /// - header values are valid UTF-8 and that record ids, date and content length can be parsed,
+    /// - there is one sentence identification per line,
+    /// - probabilities are in `[0, 1]` (see [Metadata::validate]).
+    ///
+    /// Returns an [Error::Validation] holding every issue found.
+    pub fn validate(&self) -> Result<(), Error> {
+        validation::into_result(validation::validate_document(self))
+    }
+
     /// shorthand to get url as a String
     pub fn url(&self) -> Option<String> {
         self.warc_headers()
@@ -243,27 +352,94 @@ impl Document {
         &self.metadata
     }
 
-    /// Set the document's content.
+    /// Set the document's content, and its `content-length` WARC header if present.
     pub fn set_content(&mut self, content: String) {
+        if self.warc_headers.contains_key(&WarcHeader::ContentLength) {
+            self.warc_headers.set_content_length(content.len() as u64);
+        }
         self.content = content;
     }
 }
 
+impl CorpusDocument for Document {
+    fn content(&self) -> &str {
+        &self.content
+    }
+
+    fn set_content(&mut self, content: String) {
+        Document::set_content(self, content);
+    }
+
+    fn identification(&self) -> &Identification {
+        &self.metadata.identification
+    }
+
+    fn set_identification(&mut self, identification: Identification) {
+        self.metadata.set_identification(identification);
+    }
+
+    fn sentence_identifications(&self) -> &[Option<Identification>] {
+        self.metadata.sentence_identifications()
+    }
+
+    fn set_sentence_identifications(
+        &mut self,
+        sentence_identifications: Vec<Option<Identification>>,
+    ) {
+        self.metadata
+            .set_sentence_identifications(sentence_identifications);
+    }
+
+    fn annotations(&self) -> Vec<&str> {
+        self.metadata
+            .quality_warnings()
+            .iter()
+            .map(QualityWarning::as_str)
+            .collect()
+    }
+
+    fn categories(&self) -> Vec<&str> {
+        self.metadata
+            .category_list()
+            .iter()
+            .map(Category::as_str)
+            .collect()
+    }
+
+    fn harmful_pp(&self) -> Option<f32> {
+        self.metadata.harmful_pp()
+    }
+
+    fn url(&self) -> Option<Cow<'_, str>> {
+        self.warc_headers
+            .get(&WarcHeader::TargetURI)
+            .map(|url| String::from_utf8_lossy(url))
+    }
+
+    fn record_id(&self) -> Option<Cow<'_, str>> {
+        self.warc_headers
+            .get(&WarcHeader::RecordID)
+            .map(|id| String::from_utf8_lossy(id))
+    }
+
+    fn extensions(&self) -> &Extensions {
+        self.metadata.extensions()
+    }
+
+    fn extensions_mut(&mut self) -> &mut Extensions {
+        self.metadata.extensions_mut()
+    }
+}
+
 /// custom debug implementation that converts:
-/// - `headers` from [Vec<u8>] to [String] for easier readablility
+/// - `headers` from [Vec<u8>] to [String] for easier readablility (see [WarcHeaders])
 /// - `content` from [String] to [Vec<String>] to better diagnose identification
 impl std::fmt::Debug for Document {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let headers_pp: HashMap<WarcHeader, String> = self
-            .warc_headers
-            .iter()
-            .map(|(k, v)| (k.clone(), String::from_utf8_lossy(v).to_string()))
-            .collect();
-
         let lines = &self.content.lines().collect::<Vec<&str>>();
         f.debug_struct("Document")
             .field("content (as lines())", &lines)
-            .field("warc_headers", &headers_pp)
+            .field("warc_headers", &self.warc_headers)
             .field("metadata", &self.metadata)
             .finish()
     }
@@ -271,9 +447,11 @@ impl std::fmt::Debug for Document {
 
 #[cfg(test)]
 mod tests {
+    use std::ops::Deref;
+
     use warc::{Record, WarcHeader};
 
-    use super::{Document, Metadata};
+    use super::{Category, Document, Metadata, QualityWarning};
 
     #[test]
     fn test_from_record() {
@@ -288,9 +466,9 @@ mod tests {
 
         let (headers, body) = record.into_raw_parts();
         assert_eq!(doc.content(), &String::from_utf8_lossy(&body).into_owned());
-        assert_eq!(doc.warc_headers(), &headers.headers);
+        assert_eq!(doc.warc_headers().deref(), &headers.headers);
         assert_eq!(
-            doc.warc_id(),
+            doc.warc_id().unwrap(),
             String::from_utf8_lossy(headers.headers.get(&WarcHeader::RecordID).unwrap())
                 .into_owned()
         );
@@ -308,4 +486,58 @@ mod tests {
 
         println!("{:?}", m2);
     }
+
+    #[test]
+    fn test_tags() {
+        let serialized = r#"{"identification":{"label":"en","prob":1.0},"harmful_pp":null,"tlsh":null,"quality_warnings":["tiny","custom","tiny"],"categories":null,"sentence_identifications":[]}"#;
+        let mut m: Metadata = serde_json::from_str(serialized).unwrap();
+        assert_eq!(
+            m.quality_warnings(),
+            &[
+                QualityWarning::Tiny,
+                QualityWarning::Other("custom".to_string())
+            ]
+        );
+        assert!(m.category_list().is_empty());
+        assert!(!m.is_adult());
+
+        assert!(!m.add_quality_warning(QualityWarning::Tiny));
+        assert!(m.remove_quality_warning(&QualityWarning::Other("custom".to_string())));
+        assert!(m.insert_category(Category::MixedAdult));
+        assert!(!m.insert_category("mixed_adult"));
+        assert!(m.has_category(&Category::MixedAdult));
+        assert!(m.is_adult());
+
+        assert_eq!(
+            serde_json::to_string(&m).unwrap(),
+            r#"{"identification":{"label":"en","prob":1.0},"harmful_pp":null,"tlsh":null,"quality_warnings":["tiny"],"categories":["mixed_adult"],"sentence_identifications":[]}"#
+        );
+
+        // removing the last value resets the field
+        assert!(m.remove_quality_warning(&QualityWarning::Tiny));
+        assert!(m.remove_category(&Category::MixedAdult));
+        assert_eq!(
+            serde_json::to_string(&m).unwrap(),
+            r#"{"identification":{"label":"en","prob":1.0},"harmful_pp":null,"tlsh":null,"quality_warnings":null,"categories":null,"sentence_identifications":[]}"#
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_tags() {
+        let mut m = Metadata::default();
+        assert_eq!(m.annotation(), None);
+        m.add_annotation("tiny".to_string());
+        assert_eq!(m.annotation(), Some(&vec!["tiny".to_string()]));
+
+        m.set_categories(Some(vec!["blog".to_string(), "blog".to_string()]));
+        m.add_category("adult".to_string());
+        assert_eq!(
+            m.categories(),
+            Some(&vec!["blog".to_string(), "adult".to_string()])
+        );
+        assert_eq!(m.category_list(), &[Category::Blog, Category::Adult]);
+        m.set_categories(None);
+        assert_eq!(m.categories(), None);
+    }
 }
@@ -1 +1,3 @@
+pub(crate) mod builder;
 pub(crate) mod document;
+pub(crate) mod tags;
@@ -0,0 +1,326 @@
+/*! Quality warnings and categories.
+
+Known values are enum variants, and unknown ones are kept in `Other`.
+Both are (de)serialized as plain strings, so the on-disk format is unchanged.
+!*/
+use std::fmt::Display;
+
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Defines a string-backed enum with an `Other(String)` variant,
+/// along with conversions, serde and schema implementations.
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        $name:ident {
+            $($(#[$vmeta:meta])* $variant:ident => $value:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub enum $name {
+            $($(#[$vmeta])* $variant,)*
+            /// Value unknown to oscar-io.
+            Other(String),
+        }
+
+        impl $name {
+            /// Every known value.
+            pub const KNOWN: &'static [$name] = &[$($name::$variant,)*];
+
+            /// Get the serialized form.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$variant => $value,)*
+                    Self::Other(s) => s,
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(s: &str) -> Self {
+                match s {
+                    $($value => Self::$variant,)*
+                    other => Self::Other(other.to_string()),
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(s: String) -> Self {
+                match Self::from(s.as_str()) {
+                    Self::Other(_) => Self::Other(s),
+                    known => known,
+                }
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer).map(Self::from)
+            }
+        }
+
+        impl JsonSchema for $name {
+            fn schema_name() -> String {
+                stringify!($name).to_string()
+            }
+
+            fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+                String::json_schema(gen)
+            }
+        }
+    };
+}
+
+string_enum! {
+    /// Quality warnings, set by length/content based quality filters.
+    QualityWarning {
+        /// The document has a low number of lines.
+        Tiny => "tiny",
+        /// The document has a high number of short lines.
+        ShortSentences => "short_sentences",
+        /// The document begins with short lines, likely to be boilerplate.
+        Header => "header",
+        /// The document ends with short lines, likely to be boilerplate.
+        Footer => "footer",
+        /// The document has a high proportion of punctuation.
+        Noisy => "noisy",
+        /// The document is likely to be adult content.
+        Adult => "adult",
+    }
+}
+
+string_enum! {
+    /// URL-based categories, from the [UT1 blocklist](https://dsi.ut-capitole.fr/blacklists/).
+    Category {
+        Adult => "adult",
+        Agressif => "agressif",
+        Arjel => "arjel",
+        AssociationsReligieuses => "associations_religieuses",
+        Astrology => "astrology",
+        AudioVideo => "audio-video",
+        Bank => "bank",
+        Bitcoin => "bitcoin",
+        Blog => "blog",
+        Celebrity => "celebrity",
+        Chat => "chat",
+        Child => "child",
+        Cleaning => "cleaning",
+        Cooking => "cooking",
+        Cryptojacking => "cryptojacking",
+        DangerousMaterial => "dangerous_material",
+        Dating => "dating",
+        Ddos => "ddos",
+        Dialer => "dialer",
+        Doh => "doh",
+        Download => "download",
+        Drogue => "drogue",
+        EducationalGames => "educational_games",
+        Filehosting => "filehosting",
+        Financial => "financial",
+        Forums => "forums",
+        Gambling => "gambling",
+        Games => "games",
+        Hacking => "hacking",
+        Jobsearch => "jobsearch",
+        Lingerie => "lingerie",
+        ListeBlanche => "liste_blanche",
+        ListeBu => "liste_bu",
+        Malware => "malware",
+        Manga => "manga",
+        Marketingware => "marketingware",
+        MixedAdult => "mixed_adult",
+        MobilePhone => "mobile-phone",
+        Phishing => "phishing",
+        Press => "press",
+        Publicite => "publicite",
+        Radio => "radio",
+        Reaffected => "reaffected",
+        Redirector => "redirector",
+        RemoteControl => "remote-control",
+        Sect => "sect",
+        SexualEducation => "sexual_education",
+        Shopping => "shopping",
+        Shortener => "shortener",
+        SocialNetworks => "social_networks",
+        Special => "special",
+        Sports => "sports",
+        Stalkerware => "stalkerware",
+        StrictRedirector => "strict_redirector",
+        StrongRedirector => "strong_redirector",
+        Translation => "translation",
+        Tricheur => "tricheur",
+        Update => "update",
+        Vpn => "vpn",
+        Warez => "warez",
+        Webmail => "webmail",
+    }
+}
+
+impl Category {
+    /// Categories of adult content.
+    pub fn is_adult(&self) -> bool {
+        matches!(self, Self::Adult | Self::MixedAdult | Self::Lingerie)
+    }
+}
+
+/// Tags in insertion order, without duplicates. (De)serialized as a plain list.
+///
+/// The string form of each tag is kept alongside it,
+/// so that the deprecated string accessors of [Metadata](super::Metadata) can still return references.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TagSet<T> {
+    values: Vec<T>,
+    strings: Vec<String>,
+}
+
+impl<T> TagSet<T> {
+    /// Get the tags.
+    pub(crate) fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    /// Get the string forms of the tags.
+    pub(crate) fn strings(&self) -> &Vec<String> {
+        &self.strings
+    }
+}
+
+impl<T: PartialEq + Display> TagSet<T> {
+    /// Inserts `value` if it is not already present.
+    /// Returns `true` if it has been inserted.
+    fn insert(&mut self, value: T) -> bool {
+        if self.values.contains(&value) {
+            false
+        } else {
+            self.strings.push(value.to_string());
+            self.values.push(value);
+            true
+        }
+    }
+}
+
+impl<T> Default for TagSet<T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            strings: Vec::new(),
+        }
+    }
+}
+
+impl<T: PartialEq + Display> FromIterator<T> for TagSet<T> {
+    /// Removes duplicates, keeping the first occurrence.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::default();
+        for value in iter {
+            set.insert(value);
+        }
+        set
+    }
+}
+
+impl<T: Serialize> Serialize for TagSet<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.values.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de> + PartialEq + Display> Deserialize<'de> for TagSet<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Vec::<T>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for TagSet<T> {
+    fn is_referenceable() -> bool {
+        Vec::<T>::is_referenceable()
+    }
+
+    fn schema_name() -> String {
+        Vec::<T>::schema_name()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        Vec::<T>::json_schema(gen)
+    }
+}
+
+/// Get the tags of an optional set (empty if there are none).
+pub(crate) fn values<T>(set: &Option<TagSet<T>>) -> &[T] {
+    set.as_ref().map_or(&[], TagSet::values)
+}
+
+/// Inserts `value` if it is not already present, keeping insertion order.
+/// Returns `true` if it has been inserted.
+pub(crate) fn insert<T: PartialEq + Display>(set: &mut Option<TagSet<T>>, value: T) -> bool {
+    set.get_or_insert_with(TagSet::default).insert(value)
+}
+
+/// Removes `value`. The set is reset to [None] if it was the last value, so that it serializes as `null`.
+/// Returns `true` if it was present.
+pub(crate) fn remove<T: PartialEq>(set: &mut Option<TagSet<T>>, value: &T) -> bool {
+    let Some(tags) = set else {
+        return false;
+    };
+    let Some(idx) = tags.values.iter().position(|v| v == value) else {
+        return false;
+    };
+    tags.values.remove(idx);
+    tags.strings.remove(idx);
+    if tags.values.is_empty() {
+        *set = None;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Category, QualityWarning};
+
+    #[test]
+    fn test_conversions() {
+        for warning in QualityWarning::KNOWN {
+            assert_eq!(&QualityWarning::from(warning.as_str()), warning);
+        }
+        for category in Category::KNOWN {
+            assert_eq!(&Category::from(category.to_string()), category);
+        }
+        assert_eq!(
+            QualityWarning::from("long_lines"),
+            QualityWarning::Other("long_lines".to_string())
+        );
+    }
+
+    #[test]
+    fn test_serde() {
+        let warnings: Vec<QualityWarning> =
+            serde_json::from_str(r#"["tiny","custom","short_sentences"]"#).unwrap();
+        assert_eq!(
+            warnings,
+            vec![
+                QualityWarning::Tiny,
+                QualityWarning::Other("custom".to_string()),
+                QualityWarning::ShortSentences
+            ]
+        );
+        assert_eq!(
+            serde_json::to_string(&warnings).unwrap(),
+            r#"["tiny","custom","short_sentences"]"#
+        );
+    }
+}
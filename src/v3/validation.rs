@@ -0,0 +1,195 @@
+/*! Document validation.
+
+Checks that documents have the mandatory WARC headers, that header values are well formed,
+and that metadata is consistent with the content.
+!*/
+use warc::WarcHeader;
+
+use crate::common::{WarcHeaders, MAX_PROB, MIN_PROB};
+use crate::error::{Error, ValidationError};
+
+use super::{Document, Metadata};
+
+/// Headers that are mandatory in WARC records.
+pub(crate) const REQUIRED_HEADERS: [WarcHeader; 4] = [
+    WarcHeader::RecordID,
+    WarcHeader::ContentLength,
+    WarcHeader::Date,
+    WarcHeader::WarcType,
+];
+
+/// Wraps errors into an [Error::Validation] if there are any.
+pub(crate) fn into_result(errors: Vec<ValidationError>) -> Result<(), Error> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Validation(errors))
+    }
+}
+
+fn check_prob(prob: f32, line: Option<usize>) -> Option<ValidationError> {
+    if (MIN_PROB..=MAX_PROB).contains(&f64::from(prob)) {
+        None
+    } else {
+        Some(ValidationError::ProbOutOfRange { line, prob })
+    }
+}
+
+/// Checks mandatory headers, UTF-8 validity, and parses record ids, date and content length.
+pub(crate) fn validate_headers(headers: &WarcHeaders) -> Vec<ValidationError> {
+    let mut errors: Vec<ValidationError> = REQUIRED_HEADERS
+        .iter()
+        .filter(|h| !headers.contains_key(h))
+        .map(|h| ValidationError::MissingHeader(h.clone()))
+        .collect();
+
+    let mut invalid_utf8: Vec<ValidationError> = headers
+        .iter()
+        .filter(|(_, v)| std::str::from_utf8(v).is_err())
+        .map(|(h, _)| ValidationError::InvalidUtf8(h.clone()))
+        .collect();
+    // hashmap order is not deterministic
+    invalid_utf8.sort_by_key(|e| e.to_string());
+    errors.extend(invalid_utf8);
+
+    let parsed = [
+        headers.record_id().err(),
+        headers.refers_to().err(),
+        headers.date().err(),
+        headers.content_length().err(),
+    ];
+    for e in parsed.into_iter().flatten() {
+        // UTF-8 errors have already been reported
+        if let Error::MalformedHeader(h, reason) = e {
+            if std::str::from_utf8(&headers[&h]).is_ok() {
+                errors.push(ValidationError::MalformedHeader(h, reason));
+            }
+        }
+    }
+
+    errors
+}
+
+/// Checks that probabilities are in `[0, 1]` (up to 1.001, see [MAX_PROB]).
+pub(crate) fn validate_metadata(metadata: &Metadata) -> Vec<ValidationError> {
+    let doc_prob = check_prob(*metadata.identification().prob(), None);
+    let line_probs = metadata
+        .sentence_identifications()
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, id)| id.as_ref().and_then(|id| check_prob(*id.prob(), Some(idx))));
+
+    doc_prob.into_iter().chain(line_probs).collect()
+}
+
+/// Checks metadata, and that there's one sentence identification per line of `content`.
+pub(crate) fn validate_content(content: &str, metadata: &Metadata) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+
+    let lines = content.lines().count();
+    let identifications = metadata.sentence_identifications().len();
+    if lines != identifications {
+        errors.push(ValidationError::SentenceCountMismatch {
+            lines,
+            identifications,
+        });
+    }
+
+    errors.extend(validate_metadata(metadata));
+    errors
+}
+
+/// Checks headers, metadata, and that there's one sentence identification per line.
+pub(crate) fn validate_document(doc: &Document) -> Vec<ValidationError> {
+    let mut errors = validate_headers(doc.warc_headers());
+    errors.extend(validate_content(doc.content(), doc.metadata()));
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use oxilangtag::LanguageTag;
+    use warc::{Record, WarcHeader};
+
+    use crate::common::Identification;
+    use crate::error::Error;
+    use crate::v3::{Document, Metadata};
+
+    use super::ValidationError;
+
+    fn get_doc() -> Document {
+        let record = Record::default().add_body("foo\nbar");
+        let id = Identification::new(LanguageTag::parse("fr".to_string()).unwrap(), 0.8);
+        let metadata = Metadata::new(&id, &[Some(id.clone()), None]);
+        Document::from_record(record, metadata)
+    }
+
+    #[test]
+    fn test_valid() {
+        assert!(get_doc().validate().is_ok());
+    }
+
+    #[test]
+    fn test_headers() {
+        let mut doc = get_doc();
+        let headers = doc.warc_headers_mut();
+        headers.remove(&WarcHeader::RecordID);
+        headers.insert(WarcHeader::Date, b"yesterday".to_vec());
+        headers.insert(WarcHeader::TargetURI, vec![0xff]);
+
+        assert!(doc.warc_id().is_none());
+        match doc.validate() {
+            Err(Error::Validation(errors)) => {
+                assert_eq!(errors.len(), 3);
+                assert_eq!(
+                    errors[0],
+                    ValidationError::MissingHeader(WarcHeader::RecordID)
+                );
+                assert_eq!(
+                    errors[1],
+                    ValidationError::InvalidUtf8(WarcHeader::TargetURI)
+                );
+                assert!(matches!(
+                    errors[2],
+                    ValidationError::MalformedHeader(WarcHeader::Date, _)
+                ));
+            }
+            other => panic!("unexpected result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_record_id_format() {
+        let mut doc = get_doc();
+        doc.warc_headers_mut()
+            .insert(WarcHeader::RecordID, b"1234".to_vec());
+        // non-panicking even if malformed
+        assert_eq!(doc.warc_id().as_deref(), Some("1234"));
+        assert!(doc.validate().is_err());
+    }
+
+    #[test]
+    fn test_metadata() {
+        let mut doc = get_doc();
+        let id = Identification::new(LanguageTag::parse("fr".to_string()).unwrap(), 1.5);
+        doc.metadata_mut()
+            .set_sentence_identifications(vec![Some(id)]);
+
+        match doc.validate() {
+            Err(Error::Validation(errors)) => assert_eq!(
+                errors,
+                vec![
+                    ValidationError::SentenceCountMismatch {
+                        lines: 2,
+                        identifications: 1
+                    },
+                    ValidationError::ProbOutOfRange {
+                        line: Some(0),
+                        prob: 1.5
+                    }
+                ]
+            ),
+            other => panic!("unexpected result {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,266 @@
+/*! Streaming WET ingestion.
+
+Iterates over the `conversion` records of a (gzipped) WET file and joins each of them with its [Metadata],
+usually read from a sidecar file (see [super::SidecarReader]), producing [Document]s.
+!*/
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use flate2::bufread::MultiGzDecoder;
+use warc::{RawRecordIter, WarcHeader, WarcReader};
+
+use crate::error::Error;
+
+use super::{Document, Metadata, SidecarReader};
+
+/// What to do with WET records that have no metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissingMetadata {
+    /// Silently skip the record.
+    Skip,
+    /// Yield an error for the record, and continue.
+    Error,
+    /// Use the provided metadata.
+    Fallback(Box<Metadata>),
+}
+
+/// What to do with metadata entries that have no matching WET record once the WET file has been read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingRecord {
+    /// Ignore them. They are still available through [WetReader::unmatched_metadata].
+    Ignore,
+    /// Yield a single error as last item.
+    Error,
+}
+
+/// Joins WET records with their metadata.
+pub struct WetReader<R: BufRead> {
+    records: RawRecordIter<R>,
+    metadata: HashMap<String, Metadata>,
+    missing_metadata: MissingMetadata,
+    missing_record: MissingRecord,
+    done: bool,
+}
+
+pub type WetFileReader = WetReader<BufReader<MultiGzDecoder<BufReader<File>>>>;
+
+impl<R: BufRead> WetReader<R> {
+    /// Create a new [WetReader] over uncompressed WET data.
+    /// Records without metadata are skipped and unmatched metadata is ignored by default.
+    pub fn new(r: R, metadata: HashMap<String, Metadata>) -> Self {
+        Self {
+            records: WarcReader::new(r).iter_raw_records(),
+            metadata,
+            missing_metadata: MissingMetadata::Skip,
+            missing_record: MissingRecord::Ignore,
+            done: false,
+        }
+    }
+
+    /// Set behavior for records lacking metadata.
+    pub fn with_missing_metadata(mut self, missing_metadata: MissingMetadata) -> Self {
+        self.missing_metadata = missing_metadata;
+        self
+    }
+
+    /// Set behavior for metadata lacking records.
+    pub fn with_missing_record(mut self, missing_record: MissingRecord) -> Self {
+        self.missing_record = missing_record;
+        self
+    }
+
+    /// Record ids of metadata entries that have not been matched (yet).
+    pub fn unmatched_metadata(&self) -> impl Iterator<Item = &String> {
+        self.metadata.keys()
+    }
+}
+
+impl<R: BufRead> WetReader<BufReader<MultiGzDecoder<R>>> {
+    /// Create a new [WetReader] over gzipped WET data.
+    pub fn from_gzip(r: R, metadata: HashMap<String, Metadata>) -> Self {
+        let dec = MultiGzDecoder::new(r);
+        WetReader::new(BufReader::new(dec), metadata)
+    }
+}
+
+impl WetFileReader {
+    /// Open a gzipped WET file and its (uncompressed) JSONL metadata sidecar.
+    pub fn from_paths(wet: &Path, sidecar: &Path) -> Result<Self, Error> {
+        let sidecar = BufReader::new(File::open(sidecar)?);
+        let metadata = SidecarReader::new(sidecar).into_map()?;
+        let wet = BufReader::new(File::open(wet)?);
+        Ok(WetReader::from_gzip(wet, metadata))
+    }
+}
+
+impl<R: BufRead> Iterator for WetReader<R> {
+    type Item = Result<Document, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        loop {
+            let (header, body) = match self.records.next() {
+                Some(Ok(record)) => record,
+                Some(Err(e)) => return Some(Err(e.into())),
+                None => {
+                    self.done = true;
+                    return match self.missing_record {
+                        MissingRecord::Error if !self.metadata.is_empty() => {
+                            Some(Err(Error::Custom(format!(
+                                "{} metadata entries have no matching record",
+                                self.metadata.len()
+                            ))))
+                        }
+                        _ => None,
+                    };
+                }
+            };
+            let headers = header.headers;
+
+            // skip warcinfo and others
+            if headers.get(&WarcHeader::WarcType).map(Vec::as_slice) != Some(b"conversion") {
+                continue;
+            }
+
+            let record_id = match headers.get(&WarcHeader::RecordID) {
+                Some(id) => String::from_utf8_lossy(id).into_owned(),
+                None => {
+                    return Some(Err(Error::Custom(
+                        "conversion record has no record id".to_string(),
+                    )))
+                }
+            };
+
+            let metadata = match (self.metadata.remove(&record_id), &self.missing_metadata) {
+                (Some(metadata), _) => metadata,
+                (None, MissingMetadata::Skip) => continue,
+                (None, MissingMetadata::Error) => {
+                    return Some(Err(Error::Custom(format!(
+                        "no metadata found for record {record_id}"
+                    ))))
+                }
+                (None, MissingMetadata::Fallback(metadata)) => metadata.as_ref().clone(),
+            };
+
+            let content = String::from_utf8_lossy(&body).into_owned();
+            return Some(Ok(Document::new(content, headers.into(), metadata)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::{Cursor, Write};
+
+    use flate2::{write::GzEncoder, Compression};
+    use warc::{Record, RecordType, WarcWriter};
+
+    use crate::v3::{Metadata, SidecarReader};
+
+    use super::{MissingMetadata, MissingRecord, WetReader};
+
+    /// WET file with a warcinfo record and 3 conversion records.
+    fn gen_wet() -> (Vec<u8>, Vec<String>) {
+        let mut buf = vec![];
+        let mut ids = vec![];
+        {
+            let mut w = WarcWriter::new(&mut buf);
+            let mut warcinfo = Record::default().add_body("isPartOf: CC-MAIN");
+            warcinfo.set_warc_type(RecordType::WarcInfo);
+            w.write(&warcinfo).unwrap();
+
+            for i in 0..3 {
+                let mut record = Record::default().add_body(format!("record {i}\nfoo"));
+                record.set_warc_type(RecordType::Conversion);
+                ids.push(record.warc_id().to_string());
+                w.write(&record).unwrap();
+            }
+        }
+        (buf, ids)
+    }
+
+    fn gen_sidecar(ids: &[&str]) -> HashMap<String, Metadata> {
+        let sidecar: String = ids
+            .iter()
+            .map(|id| {
+                format!(
+                    r#"{{"warc-record-id":"{id}","identification":{{"label":"fr","prob":0.9}},"sentence_identifications":[null,null]}}"#
+                ) + "\n"
+            })
+            .collect();
+        SidecarReader::new(sidecar.as_bytes()).into_map().unwrap()
+    }
+
+    #[test]
+    fn test_join() {
+        let (wet, ids) = gen_wet();
+        let metadata = gen_sidecar(&[&ids[0], &ids[2]]);
+
+        let docs: Vec<_> = WetReader::new(Cursor::new(wet), metadata)
+            .map(|x| x.unwrap())
+            .collect();
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0].content(), "record 0\nfoo");
+        assert_eq!(docs[1].warc_id().unwrap(), ids[2]);
+        assert_eq!(docs[1].identification().label().as_str(), "fr");
+    }
+
+    #[test]
+    fn test_gzip() {
+        let (wet, ids) = gen_wet();
+        let mut compressed = vec![];
+        {
+            let mut enc = GzEncoder::new(&mut compressed, Compression::fast());
+            enc.write_all(&wet).unwrap();
+        }
+        let metadata = gen_sidecar(&ids.iter().map(String::as_str).collect::<Vec<_>>());
+
+        let r = WetReader::from_gzip(Cursor::new(compressed), metadata);
+        let docs: Vec<_> = r.map(|x| x.unwrap()).collect();
+        assert_eq!(docs.len(), 3);
+    }
+
+    #[test]
+    fn test_missing_metadata() {
+        let (wet, ids) = gen_wet();
+
+        let r = WetReader::new(Cursor::new(wet.clone()), gen_sidecar(&[&ids[1]]))
+            .with_missing_metadata(MissingMetadata::Error);
+        let results: Vec<_> = r.collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_err());
+        assert!(results[1].is_ok());
+        assert!(results[2].is_err());
+
+        let r = WetReader::new(Cursor::new(wet), HashMap::new())
+            .with_missing_metadata(MissingMetadata::Fallback(Box::default()));
+        let docs: Vec<_> = r.map(|x| x.unwrap()).collect();
+        assert_eq!(docs.len(), 3);
+    }
+
+    #[test]
+    fn test_missing_record() {
+        let (wet, ids) = gen_wet();
+        let metadata = gen_sidecar(&[&ids[0], "<urn:uuid:unknown>"]);
+
+        let mut r = WetReader::new(Cursor::new(wet.clone()), metadata.clone());
+        assert!(r.next().unwrap().is_ok());
+        assert!(r.next().is_none());
+        assert_eq!(
+            r.unmatched_metadata().collect::<Vec<_>>(),
+            vec!["<urn:uuid:unknown>"]
+        );
+
+        let mut r =
+            WetReader::new(Cursor::new(wet), metadata).with_missing_record(MissingRecord::Error);
+        assert!(r.next().unwrap().is_ok());
+        assert!(r.next().unwrap().is_err());
+        assert!(r.next().is_none());
+    }
+}
@@ -70,7 +70,7 @@ impl WriterTrait for DocWriter {
 #[cfg(test)]
 mod tests {
 
-    use std::{collections::HashMap, fs::File, path::PathBuf};
+    use std::{fs::File, path::PathBuf};
 
     use oxilangtag::LanguageTag;
     use warc::WarcHeader;
@@ -78,9 +78,7 @@ mod tests {
     use crate::v3::{Document, Metadata};
 
     use super::*;
-    use crate::common::Identification;
-
-    type WarcHeaders = HashMap<WarcHeader, Vec<u8>>;
+    use crate::common::{Identification, WarcHeaders};
 
     #[test]
     fn test_init() {
@@ -122,7 +120,7 @@ Ecoutez ça va plutôt bien.";
         let doc = vec![Document::new(sentences.to_string(), headers, metadata)];
 
         wr.write(doc.clone()).unwrap();
-        wr.handle.flush();
+        wr.handle.flush().unwrap();
 
         // check if content is the same
         let _sentences = String::new();
@@ -131,7 +129,7 @@ Ecoutez ça va plutôt bien.";
         // std::thread::sleep(std::time::Duration::from_secs(50));
         let f = File::open(&pathd).unwrap();
 
-        dbg!(std::fs::read_to_string(&pathd));
+        dbg!(std::fs::read_to_string(&pathd).unwrap());
         let document: Document = serde_json::from_reader(&f).unwrap();
         let sentences = document.content();
         //to account for \n\n
@@ -146,10 +144,10 @@ Ecoutez ça va plutôt bien.";
     fn test_newline_bug() {
         // create a possibly faulty document
         let content = r#"hel\nlo\r\n"#.to_string();
-        let headers = HashMap::new();
+        let headers = WarcHeaders::default();
         let meta = Metadata::new(
             &Identification::new(LanguageTag::parse("en".to_string()).unwrap(), 1.0f32),
-            &*vec![Some(Identification::new(
+            &[Some(Identification::new(
                 LanguageTag::parse("en".to_string()).unwrap(),
                 1.0f32,
             ))],
@@ -180,4 +178,35 @@ Ecoutez ça va plutôt bien.";
 
         assert_eq!(doc, doc_from_ser);
     }
+
+    #[test]
+    fn test_unknown_fields_roundtrip() {
+        let line = r#"{"content":"foo","warc_headers":{"warc-type":"conversion"},"metadata":{"identification":{"label":"en","prob":1.0},"harmful_pp":null,"tlsh":null,"quality_warnings":null,"categories":null,"sentence_identifications":[{"label":"en","prob":1.0}],"toxicity":0.25,"provenance":{"team":"x","steps":["a","b"]}}}"#;
+        let src = tempfile::tempdir().unwrap();
+        let src_path = src.path().join("src.jsonl");
+        std::fs::write(&src_path, format!("{line}\n")).unwrap();
+
+        let docs: Vec<Document> = crate::v3::reader::DocReader::from_path(&src_path)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            docs[0].metadata().get_extension::<f32>("toxicity").unwrap(),
+            Some(0.25)
+        );
+
+        let dst = tempfile::tempdir().unwrap();
+        let mut wr = DocWriter::new(
+            dst.path(),
+            LanguageTag::parse("en".to_string()).unwrap(),
+            None,
+            None,
+        )
+        .unwrap();
+        wr.write(docs).unwrap();
+        wr.flush().unwrap();
+
+        let written = std::fs::read_to_string(dst.path().join("en.jsonl")).unwrap();
+        assert_eq!(written.trim_end(), line);
+    }
 }
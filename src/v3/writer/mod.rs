@@ -4,9 +4,11 @@
 //!
 //! The module is messy because OSCAR Schema v3 writer/reader is copied from metadata R/W from v1.1.
 mod docwriter;
+#[allow(clippy::module_inception)]
 mod writer;
 mod writertrait;
 
 pub use docwriter::DocWriter as Writer;
 pub use writer::Comp;
+pub(crate) use writer::NewWriter;
 pub use writertrait::WriterTrait;
@@ -8,6 +8,7 @@ use std::{
 
 use crate::Error;
 
+#[derive(Debug, Clone)]
 pub enum Comp {
     Zstd { level: i32 },
 }
@@ -54,9 +55,8 @@ impl NewWriter {
         })
     }
 
-    #[inline]
-
     /// Assembles a file path from a base directory, a file stem (without extensions), and a compression.
+    #[inline]
     fn assemble_filepath(dir: &Path, file_stem: &str, comp: Option<&Comp>) -> PathBuf {
         if dir.is_file() {
             dir.to_path_buf()
@@ -74,7 +74,7 @@ impl NewWriter {
 
     #[inline]
     /// Gets current filepath.
-    fn current_filepath(&self) -> PathBuf {
+    pub(crate) fn current_filepath(&self) -> PathBuf {
         // TODO simplify. Repetition because of ownership issues.
         if self.nb_files == 1 {
             Self::assemble_filepath(&self.dir, &self.file_stem, self.comp.as_ref())
@@ -155,9 +155,7 @@ impl NewWriter {
         // Create writer depending on comp
         let writer: Box<dyn Write + Send> = match comp {
             None => Box::new(BufWriter::new(f)),
-            Some(Comp::Zstd { level }) => {
-                Box::new(zstd::Encoder::new(f, *level).unwrap().auto_finish())
-            }
+            Some(Comp::Zstd { level }) => Box::new(zstd::Encoder::new(f, *level)?.auto_finish()),
         };
 
         Ok(writer)
@@ -199,9 +197,8 @@ impl Write for NewWriter {
 }
 
 #[cfg(test)]
-
 mod test {
-    use std::{fs::File, io::Write, thread, time};
+    use std::{fs::File, io::Write};
 
     use tempfile::tempdir;
 
@@ -240,7 +237,7 @@ mod test {
         let mut w = NewWriter::new(dir.path(), stem, None, Some(bound)).unwrap();
 
         let data = vec!["test\n", "1\n", "2\n", "data\n", ":)\n"];
-        let expected = vec!["test\n", "1\n2\n", "data\n", ":)\n"];
+        let expected = ["test\n", "1\n2\n", "data\n", ":)\n"];
 
         // write data
         for d in &data {
@@ -293,7 +290,7 @@ mod test {
             NewWriter::new(dir.path(), stem, Some(Comp::Zstd { level: 0 }), Some(bound)).unwrap();
 
         let data = vec!["test\n", "1\n", "2\n", "data\n", ":)\n"];
-        let expected = vec!["test\n", "1\n2\n", "data\n", ":)\n"];
+        let expected = ["test\n", "1\n2\n", "data\n", ":)\n"];
 
         // write data
         for d in &data {
@@ -0,0 +1,108 @@
+/*! WARC export/import of OSCAR documents.
+
+Documents are written back as WARC records, with their original WARC headers restored and their content as the body.
+OSCAR metadata is either carried in a `warc-json-metadata` header of the record ([MetadataMode::Header]),
+or in a companion `metadata` record referring to the document record ([MetadataMode::Record]).
+[MetadataMode::Omit] produces plain WET-style files.
+
+Both [crate::v3::Document] and [crate::oscar_doc::Document] can be exported, see [WarcDocument].
+!*/
+mod reader;
+mod writer;
+
+use std::collections::HashMap;
+
+use warc::WarcHeader;
+
+use crate::error::Error;
+use crate::{oscar_doc, v3};
+
+pub use reader::Reader;
+pub use writer::Writer;
+
+/// Header holding the JSON-serialized OSCAR metadata when using [MetadataMode::Header].
+pub const METADATA_HEADER: &str = "warc-json-metadata";
+
+/// Where to store OSCAR metadata in the WARC output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataMode {
+    /// Serialize metadata as JSON in a [METADATA_HEADER] header of the document record.
+    Header,
+    /// Serialize metadata as JSON in the body of a `metadata` record following the document record.
+    Record,
+    /// Do not store metadata (WET-style output). Files written this way can't be read back by [Reader].
+    Omit,
+}
+
+/// Conversion between a document and its WARC record parts.
+pub trait WarcDocument: Sized {
+    /// Get the document WARC headers, as they'd be found in a WARC record.
+    fn warc_record_headers(&self) -> HashMap<WarcHeader, Vec<u8>>;
+
+    /// Get the document content, that is the record body.
+    fn warc_record_body(&self) -> &str;
+
+    /// Get the JSON-serialized metadata.
+    fn warc_record_metadata(&self) -> Result<String, Error>;
+
+    /// Build a document back from its WARC record parts.
+    fn from_warc_record(
+        headers: HashMap<WarcHeader, Vec<u8>>,
+        body: Vec<u8>,
+        metadata: &[u8],
+    ) -> Result<Self, Error>;
+}
+
+impl WarcDocument for v3::Document {
+    fn warc_record_headers(&self) -> HashMap<WarcHeader, Vec<u8>> {
+        self.warc_headers().clone().into()
+    }
+
+    fn warc_record_body(&self) -> &str {
+        self.content()
+    }
+
+    fn warc_record_metadata(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(self.metadata())?)
+    }
+
+    fn from_warc_record(
+        headers: HashMap<WarcHeader, Vec<u8>>,
+        body: Vec<u8>,
+        metadata: &[u8],
+    ) -> Result<Self, Error> {
+        let metadata: v3::Metadata = serde_json::from_slice(metadata)?;
+        Ok(v3::Document::new(
+            String::from_utf8(body)?,
+            headers.into(),
+            metadata,
+        ))
+    }
+}
+
+impl WarcDocument for oscar_doc::Document {
+    fn warc_record_headers(&self) -> HashMap<WarcHeader, Vec<u8>> {
+        self.warc_headers().clone().into()
+    }
+
+    fn warc_record_body(&self) -> &str {
+        self.content()
+    }
+
+    fn warc_record_metadata(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(self.metadata())?)
+    }
+
+    fn from_warc_record(
+        headers: HashMap<WarcHeader, Vec<u8>>,
+        body: Vec<u8>,
+        metadata: &[u8],
+    ) -> Result<Self, Error> {
+        let metadata: oscar_doc::Metadata = serde_json::from_slice(metadata)?;
+        Ok(oscar_doc::Document::new(
+            String::from_utf8(body)?,
+            headers.into(),
+            metadata,
+        ))
+    }
+}
@@ -0,0 +1,166 @@
+//! WARC document reader.
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::marker::PhantomData;
+
+use flate2::bufread::MultiGzDecoder;
+use warc::{RawRecordIter, WarcHeader, WarcReader};
+
+use crate::error::Error;
+
+use super::{WarcDocument, METADATA_HEADER};
+
+type RecordParts = (HashMap<WarcHeader, Vec<u8>>, Vec<u8>);
+
+/// Reads documents written by [super::Writer], in either [super::MetadataMode::Header] or [super::MetadataMode::Record] mode.
+///
+/// `warcinfo` records are skipped.
+pub struct Reader<R: BufRead, D> {
+    records: RawRecordIter<R>,
+    // document record waiting for its metadata record
+    pending: Option<RecordParts>,
+    // result to yield before reading further records
+    queued: Option<Result<D, Error>>,
+    doc_type: PhantomData<D>,
+}
+
+impl<R: BufRead, D: WarcDocument> Reader<R, D> {
+    /// Create a new [Reader] on uncompressed WARC data.
+    pub fn new(r: R) -> Self {
+        Self {
+            records: WarcReader::new(r).iter_raw_records(),
+            pending: None,
+            queued: None,
+            doc_type: PhantomData,
+        }
+    }
+}
+
+impl<R: BufRead, D: WarcDocument> Reader<BufReader<MultiGzDecoder<R>>, D> {
+    /// Create a new [Reader] on gzipped WARC data (single or multiple gzip members).
+    pub fn from_gzip(r: R) -> Self {
+        let dec = MultiGzDecoder::new(r);
+        Reader::new(BufReader::new(dec))
+    }
+}
+
+/// Error for a document record that has no metadata.
+fn missing_metadata(headers: &HashMap<WarcHeader, Vec<u8>>) -> Error {
+    let record_id = headers
+        .get(&WarcHeader::RecordID)
+        .map(|id| String::from_utf8_lossy(id).into_owned());
+    Error::Custom(format!("no metadata found for record {record_id:?}"))
+}
+
+impl<R: BufRead, D: WarcDocument> Iterator for Reader<R, D> {
+    type Item = Result<D, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(queued) = self.queued.take() {
+            return Some(queued);
+        }
+
+        loop {
+            let (header, body) = match self.records.next() {
+                Some(Ok(record)) => record,
+                Some(Err(e)) => return Some(Err(e.into())),
+                // a document record may still wait for its metadata
+                None => return self.pending.take().map(|(h, _)| Err(missing_metadata(&h))),
+            };
+            let mut headers = header.headers;
+
+            match headers.get(&WarcHeader::WarcType).map(Vec::as_slice) {
+                Some(b"warcinfo") => continue,
+                Some(b"metadata") => {
+                    let (doc_headers, doc_body) = match self.pending.take() {
+                        Some(pending) => pending,
+                        None => {
+                            return Some(Err(Error::Custom(
+                                "metadata record without a preceding document record".to_string(),
+                            )))
+                        }
+                    };
+
+                    if headers.get(&WarcHeader::RefersTo) != doc_headers.get(&WarcHeader::RecordID)
+                    {
+                        return Some(Err(missing_metadata(&doc_headers)));
+                    }
+
+                    return Some(D::from_warc_record(doc_headers, doc_body, &body));
+                }
+                _ => {
+                    let previous = self.pending.take();
+                    let current =
+                        match headers.remove(&WarcHeader::Unknown(METADATA_HEADER.to_string())) {
+                            Some(metadata) => Some(D::from_warc_record(headers, body, &metadata)),
+                            None => {
+                                self.pending = Some((headers, body));
+                                None
+                            }
+                        };
+
+                    match (previous, current) {
+                        (None, None) => continue,
+                        (None, Some(doc)) => return Some(doc),
+                        (Some((previous_headers, _)), current) => {
+                            self.queued = current;
+                            return Some(Err(missing_metadata(&previous_headers)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use warc::Record;
+
+    use crate::v3::{Document, Metadata};
+    use crate::warc_io::{MetadataMode, Writer};
+
+    use super::Reader;
+
+    #[test]
+    fn test_missing_metadata() {
+        let record = Record::default().add_body("foo").into_raw_parts();
+        let doc = Document::new(
+            "foo".to_string(),
+            record.0.headers.into(),
+            Metadata::default(),
+        );
+
+        let mut w = Writer::new(vec![], MetadataMode::Omit);
+        w.write(&doc).unwrap();
+        let buf = w.into_inner();
+
+        let mut r: Reader<_, Document> = Reader::new(Cursor::new(buf));
+        assert!(r.next().unwrap().is_err());
+        assert!(r.next().is_none());
+    }
+
+    #[test]
+    fn test_skip_warcinfo() {
+        let mut warcinfo = Record::default().add_body("software: oscar-io");
+        warcinfo.set_warc_type(warc::RecordType::WarcInfo);
+        let mut buf = vec![];
+        warc::WarcWriter::new(&mut buf).write(&warcinfo).unwrap();
+
+        let record = Record::default().add_body("foo").into_raw_parts();
+        let doc = Document::new(
+            "foo".to_string(),
+            record.0.headers.into(),
+            Metadata::default(),
+        );
+        let mut w = Writer::new(buf, MetadataMode::Header);
+        w.write(&doc).unwrap();
+        let buf = w.into_inner();
+
+        let r: Reader<_, Document> = Reader::new(Cursor::new(buf));
+        let docs: Vec<Document> = r.map(|x| x.unwrap()).collect();
+        assert_eq!(docs, vec![doc]);
+    }
+}
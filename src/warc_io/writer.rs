@@ -0,0 +1,242 @@
+//! WARC document writer.
+use std::collections::HashMap;
+use std::io::Write;
+
+use chrono::{SecondsFormat, Utc};
+use flate2::{write::GzEncoder, Compression};
+use warc::{BufferedBody, RawRecordHeader, Record, WarcHeader, WarcWriter};
+
+use crate::error::Error;
+
+use super::{MetadataMode, WarcDocument, METADATA_HEADER};
+
+/// Writes documents as WARC records.
+///
+/// Missing mandatory headers (`warc-record-id`, `warc-type`, `warc-date`) are generated,
+/// and `content-length` is always set to the length of the (possibly edited) content.
+pub struct Writer<W: Write> {
+    w: W,
+    mode: MetadataMode,
+    gzip: bool,
+}
+
+impl<W: Write> Writer<W> {
+    /// Create a new uncompressed [Writer].
+    pub fn new(w: W, mode: MetadataMode) -> Self {
+        Self {
+            w,
+            mode,
+            gzip: false,
+        }
+    }
+
+    /// Create a new [Writer] that compresses each record in its own gzip member,
+    /// like Common Crawl WARC/WET files.
+    pub fn new_gzip(w: W, mode: MetadataMode) -> Self {
+        Self {
+            w,
+            mode,
+            gzip: true,
+        }
+    }
+
+    /// Write a single document (and its metadata record if applicable).
+    ///
+    /// Does not call [Self::flush].
+    pub fn write<D: WarcDocument>(&mut self, doc: &D) -> Result<(), Error> {
+        let mut headers = doc.warc_record_headers();
+        let body = doc.warc_record_body().as_bytes();
+
+        headers.insert(WarcHeader::ContentLength, body.len().to_string().into());
+        headers
+            .entry(WarcHeader::WarcType)
+            .or_insert_with(|| "conversion".into());
+        let record_id = headers
+            .entry(WarcHeader::RecordID)
+            .or_insert_with(|| Record::<BufferedBody>::generate_record_id().into())
+            .clone();
+        let date = headers
+            .entry(WarcHeader::Date)
+            .or_insert_with(|| Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true).into())
+            .clone();
+
+        match self.mode {
+            MetadataMode::Header => {
+                headers.insert(
+                    WarcHeader::Unknown(METADATA_HEADER.to_string()),
+                    doc.warc_record_metadata()?.into(),
+                );
+                self.write_record(headers, body)?;
+            }
+            MetadataMode::Record => {
+                self.write_record(headers, body)?;
+
+                let metadata = doc.warc_record_metadata()?;
+                let metadata_headers = vec![
+                    (WarcHeader::WarcType, "metadata".into()),
+                    (
+                        WarcHeader::RecordID,
+                        Record::<BufferedBody>::generate_record_id().into(),
+                    ),
+                    (WarcHeader::RefersTo, record_id),
+                    (WarcHeader::Date, date),
+                    (WarcHeader::ContentType, "application/json".into()),
+                    (WarcHeader::ContentLength, metadata.len().to_string().into()),
+                ]
+                .into_iter()
+                .collect();
+                self.write_record(metadata_headers, metadata.as_bytes())?;
+            }
+            MetadataMode::Omit => self.write_record(headers, body)?,
+        }
+
+        Ok(())
+    }
+
+    /// calls [Self::write] for each document, returning an error if there's any failure, then calls [Self::flush].
+    pub fn write_multiple<D: WarcDocument>(&mut self, docs: &[D]) -> Result<(), Error> {
+        for doc in docs {
+            self.write(doc)?;
+        }
+        self.flush()
+    }
+
+    /// Maps to [std::io::Write::flush] method on the inner writer.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        Ok(self.w.flush()?)
+    }
+
+    /// Get back the inner writer.
+    pub fn into_inner(self) -> W {
+        self.w
+    }
+
+    /// Serializes a single record and writes it, compressing it in its own gzip member if needed.
+    fn write_record(
+        &mut self,
+        headers: HashMap<WarcHeader, Vec<u8>>,
+        body: &[u8],
+    ) -> Result<(), Error> {
+        let mut buf = Vec::with_capacity(body.len() + 1024);
+        WarcWriter::new(&mut buf).write_raw(
+            RawRecordHeader {
+                version: "1.0".to_string(),
+                headers,
+            },
+            &body,
+        )?;
+
+        if self.gzip {
+            let mut enc = GzEncoder::new(&mut self.w, Compression::default());
+            enc.write_all(&buf)?;
+            enc.finish()?;
+        } else {
+            self.w.write_all(&buf)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::File,
+        io::{BufReader, Cursor},
+    };
+
+    use warc::{Record, WarcHeader};
+
+    use crate::oscar_doc;
+    use crate::v3::{Document, Metadata};
+    use crate::warc_io::{MetadataMode, Reader};
+
+    use super::Writer;
+
+    fn get_v3_docs() -> Vec<Document> {
+        (0..5)
+            .map(|i| {
+                let record = Record::default()
+                    .add_body(format!("document {i}\nsecond line"))
+                    .into_raw_parts();
+                let mut headers = record.0.headers;
+                headers.insert(WarcHeader::TargetURI, format!("https://foo.bar/{i}").into());
+                let mut metadata = Metadata::default();
+                metadata.set_harmful_pp(Some(i as f32));
+                Document::new(
+                    String::from_utf8(record.1).unwrap(),
+                    headers.into(),
+                    metadata,
+                )
+            })
+            .collect()
+    }
+
+    fn get_oscar_docs() -> Vec<oscar_doc::Document> {
+        let f = File::open("tests/res/data.jsonl").unwrap();
+        oscar_doc::Reader::new(BufReader::new(f))
+            .map(|x| x.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_roundtrip_v3() {
+        let docs = get_v3_docs();
+        for mode in [MetadataMode::Header, MetadataMode::Record] {
+            let mut w = Writer::new(vec![], mode);
+            w.write_multiple(&docs).unwrap();
+            let buf = w.into_inner();
+
+            let r: Reader<_, Document> = Reader::new(Cursor::new(buf));
+            let docs_from_warc: Vec<Document> = r.map(|x| x.unwrap()).collect();
+            assert_eq!(docs, docs_from_warc);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_gzip() {
+        let docs = get_oscar_docs();
+        for mode in [MetadataMode::Header, MetadataMode::Record] {
+            let mut w = Writer::new_gzip(vec![], mode);
+            w.write_multiple(&docs).unwrap();
+            let buf = w.into_inner();
+
+            let r: Reader<_, oscar_doc::Document> = Reader::from_gzip(Cursor::new(buf));
+            let docs_from_warc: Vec<oscar_doc::Document> = r.map(|x| x.unwrap()).collect();
+
+            assert!(!docs_from_warc.is_empty());
+            assert_eq!(docs.len(), docs_from_warc.len());
+            for (doc, doc_from_warc) in docs.iter().zip(&docs_from_warc) {
+                assert_eq!(doc.content(), doc_from_warc.content());
+                assert_eq!(doc.metadata(), doc_from_warc.metadata());
+                assert_eq!(
+                    doc.warc_headers().record_id().unwrap(),
+                    doc_from_warc.warc_headers().record_id().unwrap()
+                );
+                assert_eq!(
+                    doc_from_warc.warc_headers().content_length().unwrap(),
+                    Some(doc.content().len() as u64)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_omit_metadata() {
+        let docs = get_v3_docs();
+        let mut w = Writer::new(vec![], MetadataMode::Omit);
+        w.write_multiple(&docs).unwrap();
+        let buf = w.into_inner();
+
+        // records are readable by the warc crate
+        let records: Vec<_> = warc::WarcReader::new(Cursor::new(buf))
+            .iter_records()
+            .collect();
+        assert_eq!(records.len(), docs.len());
+        for (record, doc) in records.into_iter().zip(&docs) {
+            let record = record.unwrap();
+            assert_eq!(record.body(), doc.content().as_bytes());
+            assert_eq!(Some(record.warc_id()), doc.warc_id().as_deref());
+        }
+    }
+}
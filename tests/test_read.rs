@@ -46,7 +46,7 @@ fn test_split_validity() {
     assert!(docs_from_split.iter().all(Result::is_ok));
     let docs_from_split: Vec<Document> = docs_from_split.into_iter().map(|x| x.unwrap()).collect();
 
-    assert!(docs_from_full.len() != 0);
+    assert!(!docs_from_full.is_empty());
     assert_eq!(docs_from_full.len(), docs_from_split.len());
     assert_eq!(docs_from_full, docs_from_split);
 }